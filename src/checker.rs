@@ -0,0 +1,458 @@
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use futures::future::join_all;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream; // TcpStream async dari Tokio
+
+use crate::config::{ProbeConfig, ResolverConfig, RetryConfig, TlsConfig};
+use crate::error::CheckError;
+use crate::geo::GeoDatabases;
+use crate::http;
+use crate::politeness::RateLimiter;
+use crate::resolver;
+use crate::retry::RetryPolicy;
+use crate::tls::TlsClient;
+use crate::{CheckMeta, ProxyData, Result};
+
+// 一次检测各阶段耗时（毫秒）
+// ttfb_ms 从发出 HTTP 请求算起，total_ms 从开始建立 TCP 连接算起
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Timings {
+    pub connect_ms: u32,
+    pub tls_handshake_ms: u32,
+    pub ttfb_ms: u32,
+    pub total_ms: u32,
+}
+
+// speed.cloudflare.com/meta 返回的字段（JSON 为 camelCase，输出统一为 snake_case）
+// 缺失、为 null 或类型不符的字段按空值处理，只有 clientIp 是判断存活所必需的
+// /cdn-cgi/trace 的结果也映射到这里，tls_version 只有 trace 格式提供
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default, rename_all(deserialize = "camelCase"))]
+pub struct CfMeta {
+    #[serde(deserialize_with = "lenient_string")]
+    pub client_ip: String,
+    #[serde(deserialize_with = "lenient_string")]
+    pub colo: String,
+    #[serde(deserialize_with = "lenient_u32")]
+    pub asn: u32,
+    #[serde(deserialize_with = "lenient_string")]
+    pub as_organization: String,
+    #[serde(deserialize_with = "lenient_string")]
+    pub country: String,
+    #[serde(deserialize_with = "lenient_string")]
+    pub city: String,
+    #[serde(deserialize_with = "lenient_string")]
+    pub region: String,
+    #[serde(deserialize_with = "lenient_string")]
+    pub http_protocol: String,
+    #[serde(deserialize_with = "lenient_string")]
+    pub tls_version: String,
+}
+
+// 数字按字符串写出，其他类型视为空
+fn lenient_string<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<String, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::String(s) => s,
+        Value::Number(n) => n.to_string(),
+        _ => String::new(),
+    })
+}
+
+// 接受数字或数字字符串（"13335"、"AS13335"），其他情况为 0
+fn lenient_u32<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<u32, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Number(n) => n.as_u64().and_then(|n| u32::try_from(n).ok()).unwrap_or_default(),
+        Value::String(s) => {
+            let s = s.trim();
+            let digits = s.strip_prefix("AS").or_else(|| s.strip_prefix("as")).unwrap_or(s);
+            digits.parse().unwrap_or_default()
+        }
+        _ => 0,
+    })
+}
+
+// 各阶段独立的超时：死主机在 connect 阶段很快失败，慢但可用的代理仍有完整的读取时间
+// total 限制整次检测（连接 + 握手 + 请求 + 读取完整响应）
+#[derive(Debug, Clone, Copy)]
+pub struct Timeouts {
+    pub connect: Duration,
+    pub handshake: Duration,
+    pub first_byte: Duration,
+    pub total: Duration,
+}
+
+impl Timeouts {
+    pub fn from_config(probe: &ProbeConfig) -> Self {
+        Timeouts {
+            connect: Duration::from_millis(probe.connect_timeout_ms),
+            handshake: Duration::from_millis(probe.handshake_timeout_ms),
+            first_byte: Duration::from_millis(probe.first_byte_timeout_ms),
+            total: Duration::from_secs(probe.timeout_seconds),
+        }
+    }
+}
+
+// 单个 resolver 的检测结果：出口信息、耗时与尝试次数
+type Echo = (CfMeta, Timings, u32);
+
+fn elapsed_ms(since: Instant) -> u32 {
+    since.elapsed().as_millis().min(u32::MAX as u128) as u32
+}
+
+// 代理检测器：持有回显服务列表、共享的 TLS 客户端、GeoIP 数据库以及本机原始出口 IP
+pub struct Checker {
+    resolvers: Vec<ResolverConfig>,
+    resolvers_per_check: usize,
+    quorum: usize,
+    // 轮询起点，使请求均匀分布到各 resolver
+    next_resolver: AtomicUsize,
+    timeouts: Timeouts,
+    max_body_bytes: usize,
+    // None 表示不做 TCP 预检
+    precheck_timeout: Option<Duration>,
+    retry: RetryPolicy,
+    tls: TlsClient,
+    // 经代理建立的每个连接（含重试）都要先取得令牌，None 表示不限速
+    pub rate_limit: Option<RateLimiter>,
+    // 逐个访问 resolver：设置了单 IP / 网段并发上限时，一次检测只占一个连接，与 HostLimiter 的名额一致
+    pub sequential: bool,
+    pub geo: GeoDatabases,
+    // 各 resolver 看到的本机出口 IP（IPv4/IPv6 可能不同）
+    pub original_ips: Vec<String>,
+}
+
+impl Checker {
+    // 先不经代理直连各回显服务，记录本机出口 IP 用于后续比对
+    // 直连失败的 resolver 会被跳过，全部失败时报错
+    pub async fn new(probe: &ProbeConfig, tls: &TlsConfig, retry: &RetryConfig, geo: GeoDatabases) -> Result<Self> {
+        if probe.resolvers.is_empty() {
+            return Err("No resolvers configured".into());
+        }
+        let retry = RetryPolicy::new(retry)?;
+
+        let timeouts = Timeouts::from_config(probe);
+        let tls = TlsClient::new(tls)?;
+
+        let mut resolvers = Vec::new();
+        let mut original_ips = Vec::new();
+        for resolver in &probe.resolvers {
+            // Get original IP (without proxy)
+            let result = match check_connection(&tls, &resolver.host, &resolver.path, None, timeouts, probe.max_body_bytes).await {
+                Ok((body, _timings)) => resolver::parse_echo(resolver, &body),
+                Err(e) => Err(e),
+            };
+
+            match result {
+                Ok(meta) => {
+                    eprintln!("Original IP via {} (SNI {}): {}", resolver, tls.sni(&resolver.host), meta.client_ip);
+                    if !original_ips.contains(&meta.client_ip) {
+                        original_ips.push(meta.client_ip);
+                    }
+                    resolvers.push(resolver.clone());
+                }
+                Err(e) => eprintln!("⚠️ Resolver {} unavailable, skipping it: {}", resolver, e),
+            }
+        }
+
+        // If no resolver is reachable, no checks can be done.
+        if resolvers.is_empty() {
+            return Err("Failed to get original IP info from any resolver".into());
+        }
+
+        let resolvers_per_check = match probe.resolvers_per_check {
+            0 => resolvers.len(),
+            n => n.min(resolvers.len()),
+        };
+        if probe.quorum == 0 || probe.quorum > resolvers_per_check {
+            return Err(format!(
+                "quorum must be between 1 and {} (resolvers used per check), got {}",
+                resolvers_per_check, probe.quorum
+            ).into());
+        }
+        if resolvers_per_check > 1 {
+            eprintln!(
+                "Checking each proxy against {} of {} resolvers, quorum {}",
+                resolvers_per_check, resolvers.len(), probe.quorum
+            );
+        }
+
+        Ok(Checker {
+            resolvers,
+            resolvers_per_check,
+            quorum: probe.quorum,
+            next_resolver: AtomicUsize::new(0),
+            timeouts,
+            max_body_bytes: probe.max_body_bytes,
+            precheck_timeout: probe.tcp_precheck.then(|| Duration::from_millis(probe.precheck_timeout_ms)),
+            retry,
+            tls,
+            rate_limit: None,
+            sequential: false,
+            geo,
+            original_ips,
+        })
+    }
+
+    // 通过代理访问多个回显服务，至少 quorum 个 resolver 看到相同的出口 IP 且未被黑名单过滤时返回带地理信息的代理数据
+    // 出口信息与耗时取自多数组中第一个成功的 resolver
+    pub async fn check(&self, ip: &str, port: u16) -> std::result::Result<(ProxyData, CheckMeta), CheckError> {
+        let start = self.next_resolver.fetch_add(1, Ordering::Relaxed);
+        let selected: Vec<&ResolverConfig> = (0..self.resolvers_per_check)
+            .map(|i| &self.resolvers[(start + i) % self.resolvers.len()])
+            .collect();
+
+        let results = if self.sequential {
+            let mut results = Vec::with_capacity(selected.len());
+            for resolver in &selected {
+                results.push(self.probe(resolver, ip, port).await);
+            }
+            results
+        } else {
+            join_all(selected.iter().map(|resolver| self.probe(resolver, ip, port))).await
+        };
+
+        // 按出口 IP 分组，组内保持 resolver 顺序
+        let mut groups: Vec<Vec<(&ResolverConfig, Echo)>> = Vec::new();
+        let mut first_error = None;
+        let mut passed = 0;
+        for (resolver, result) in selected.iter().zip(results) {
+            match result {
+                Ok(echo) => {
+                    passed += 1;
+                    match groups.iter_mut().find(|group| same_ip(&group[0].1 .0.client_ip, &echo.0.client_ip)) {
+                        Some(group) => group.push((*resolver, echo)),
+                        None => groups.push(vec![(*resolver, echo)]),
+                    }
+                }
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+
+        // 全部失败时返回第一个错误，保留具体的失败原因
+        if passed == 0 {
+            return Err(first_error.unwrap_or(CheckError::MissingClientIp));
+        }
+        if passed < self.quorum {
+            return Err(CheckError::QuorumNotMet { passed, required: self.quorum });
+        }
+        // 人数相同时取先出现的一组；max_by_key 取最后一个，因此反向查找
+        let group = groups.into_iter().rev().max_by_key(|group| group.len()).expect("at least one resolver passed");
+        let agreed = group.len();
+        if agreed < self.quorum {
+            return Err(CheckError::ExitIpDisagreement { agreed, passed, required: self.quorum });
+        }
+        let (resolver, (cf_meta, timings, attempts)) = group.into_iter().next().expect("groups are never empty");
+
+        // 入口 IP 与 Cloudflare 看到的出口 IP 可能不同，两者都要检查黑名单并查询地理信息
+        let exit_differs = !same_ip(ip, &cf_meta.client_ip);
+
+        let entry_blocklist = self.geo.filter_reason(ip);
+        let exit_blocklist = if exit_differs {
+            self.geo.filter_reason(&cf_meta.client_ip)
+        } else {
+            entry_blocklist.clone()
+        };
+
+        if let Some(rejection) = self.geo.rejection(entry_blocklist.as_ref(), exit_blocklist.as_ref()) {
+            return Err(rejection);
+        }
+
+        // 获取地理位置与 ASN 信息
+        let geo_info = self.geo.lookup(ip);
+        let exit_geo = if exit_differs {
+            self.geo.lookup(&cf_meta.client_ip)
+        } else {
+            geo_info.clone()
+        };
+
+        let proxy_data = ProxyData {
+            ip: ip.to_string(),
+            port,
+            country_code: geo_info.country_code,
+            country_name: geo_info.country_name,
+            city_code: geo_info.city_code,
+            city_name: geo_info.city_name,
+            asn_number: geo_info.asn_number,
+            org_name: geo_info.org_name,
+            timings,
+            cf: cf_meta,
+            exit_differs,
+            exit_geo,
+            entry_blocklist: entry_blocklist.map(|kind| kind.to_string()).unwrap_or_default(),
+            exit_blocklist: exit_blocklist.map(|kind| kind.to_string()).unwrap_or_default(),
+            attempts,
+            source: String::new(),
+        };
+
+        let meta = CheckMeta {
+            checked_at: chrono::Utc::now(),
+            resolver: resolver.to_string(),
+            resolvers_passed: agreed,
+            resolvers_checked: selected.len(),
+        };
+
+        Ok((proxy_data, meta))
+    }
+
+    // 只建立 TCP 连接就关闭，用于在完整检测前快速排除未开放的端口；未启用预检时直接通过
+    pub async fn precheck(&self, ip: IpAddr, port: u16) -> std::result::Result<(), CheckError> {
+        let Some(timeout) = self.precheck_timeout else {
+            return Ok(());
+        };
+        if let Some(rate_limit) = &self.rate_limit {
+            rate_limit.acquire().await;
+        }
+        tokio::time::timeout(timeout, TcpStream::connect(SocketAddr::new(ip, port)))
+            .await
+            .map_err(|_| CheckError::ConnectTimeout)?
+            .map_err(CheckError::from_connect_error)?;
+        Ok(())
+    }
+
+    // 按重试策略经代理请求单个 resolver，成功时同时返回所用的尝试次数
+    async fn probe(&self, resolver: &ResolverConfig, ip: &str, port: u16) -> std::result::Result<Echo, CheckError> {
+        let mut attempt = 1;
+        loop {
+            match self.probe_once(resolver, ip, port).await {
+                Ok((cf_meta, timings)) => return Ok((cf_meta, timings, attempt)),
+                Err(e) if self.retry.should_retry(&e, attempt) => {
+                    tokio::time::sleep(self.retry.backoff(attempt)).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    // 看到本机原始 IP 说明代理是透明的，不算通过
+    async fn probe_once(&self, resolver: &ResolverConfig, ip: &str, port: u16) -> std::result::Result<(CfMeta, Timings), CheckError> {
+        if let Some(rate_limit) = &self.rate_limit {
+            rate_limit.acquire().await;
+        }
+        let (body, timings) = check_connection(&self.tls, &resolver.host, &resolver.path, Some((ip, port)), self.timeouts, self.max_body_bytes).await?;
+        let cf_meta = resolver::parse_echo(resolver, &body)?;
+        if self.original_ips.contains(&cf_meta.client_ip) {
+            return Err(CheckError::SameAsOrigin);
+        }
+        Ok((cf_meta, timings))
+    }
+}
+
+// 按地址比较，避免 IPv6 不同写法被误判为不同 IP
+fn same_ip(a: &str, b: &str) -> bool {
+    match (a.parse::<IpAddr>(), b.parse::<IpAddr>()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+// 总超时发生时所处的阶段，用于区分 connect/握手/读取超时
+#[derive(Clone, Copy)]
+enum Phase {
+    Connect,
+    Handshake,
+    Read,
+}
+
+pub async fn check_connection(
+    tls: &TlsClient,
+    host: &str,
+    path: &str,
+    proxy: Option<(&str, u16)>,
+    timeouts: Timeouts,
+    max_body_bytes: usize,
+) -> std::result::Result<(Vec<u8>, Timings), CheckError> {
+    let mut timings = Timings::default();
+    let mut phase = Phase::Connect;
+    let started = Instant::now();
+
+    // Bungkus seluruh operasi koneksi dalam tokio::time::timeout (batas total),
+    // setiap fase juga punya batas waktunya sendiri
+    let result = tokio::time::timeout(timeouts.total, async {
+        // Build HTTP request payload
+        let payload = format!(
+            "GET {} HTTP/1.1\r\n\
+             Host: {}\r\n\
+             User-Agent: Mozilla/5.0 (Windows NT 10.0) AppleWebKit/537.36 \
+             (KHTML, like Gecko) Chrome/42.0.2311.135 Safari/537.36 Edge/12.10240\r\n\
+             Connection: close\r\n\r\n",
+            path, host
+        );
+
+        // Create TCP connection
+        let connect = async {
+            if let Some((proxy_ip, proxy_port)) = proxy {
+                // Menangani alamat IPv6 dengan benar dengan membungkusnya dalam kurung siku.
+                let connect_addr = if proxy_ip.contains(':') {
+                    // Ini adalah alamat IPv6, formatnya menjadi "[ipv6]:port"
+                    format!("[{}]:{}", proxy_ip, proxy_port)
+                } else {
+                    // Ini adalah alamat IPv4, formatnya tetap "ipv4:port"
+                    format!("{}:{}", proxy_ip, proxy_port)
+                };
+                TcpStream::connect(connect_addr).await
+            } else {
+                // Connect directly to host (Tokio's connect can resolve hostnames)
+                TcpStream::connect(format!("{}:443", host)).await
+            }
+        };
+        let stream = tokio::time::timeout(timeouts.connect, connect)
+            .await
+            .map_err(|_| CheckError::ConnectTimeout)?
+            .map_err(CheckError::from_connect_error)?;
+        timings.connect_ms = elapsed_ms(started);
+        phase = Phase::Handshake;
+
+        // Create TLS connection (SNI dari TlsClient, bisa berbeda dari Host header)
+        let handshake_started = Instant::now();
+        let tls_stream = tokio::time::timeout(timeouts.handshake, tls.connect(host, stream))
+            .await
+            .map_err(|_| CheckError::TlsHandshakeTimeout)??;
+        timings.tls_handshake_ms = elapsed_ms(handshake_started);
+        phase = Phase::Read;
+
+        // Send HTTP request, lalu tunggu byte pertama respons dalam batas first_byte
+        let request_sent = Instant::now();
+        let mut reader = BufReader::new(tls_stream);
+        let first_byte = async {
+            reader.get_mut().write_all(payload.as_bytes()).await?;
+            reader.fill_buf().await.map(|_| ())
+        };
+        tokio::time::timeout(timeouts.first_byte, first_byte)
+            .await
+            .map_err(|_| CheckError::FirstByteTimeout)?
+            .map_err(CheckError::from_io_error)?;
+        timings.ttfb_ms = elapsed_ms(request_sent);
+
+        // Read response: status line dan header dulu, body hanya dibaca untuk status 200
+        let head = http::read_head(&mut reader).await?;
+        if head.status != 200 {
+            return Err(CheckError::HttpStatus(head.status));
+        }
+
+        let body = http::read_body(&mut reader, &head, max_body_bytes).await?;
+        Ok(body)
+    }).await;
+
+    let body = match result {
+        Ok(inner_result) => inner_result?, // Hasil dari blok async (bisa Ok atau Err)
+        // Error karena timeout
+        Err(_) => {
+            return Err(match phase {
+                Phase::Connect => CheckError::ConnectTimeout,
+                Phase::Handshake => CheckError::TlsHandshakeTimeout,
+                Phase::Read => CheckError::ReadTimeout,
+            })
+        }
+    };
+    timings.total_ms = elapsed_ms(started);
+
+    Ok((body, timings))
+}
@@ -0,0 +1,296 @@
+use clap::{Args, Parser, Subcommand};
+
+use crate::config::ResolverConfig;
+use crate::output::OutputFormat;
+
+// 命令行参数定义
+// 未指定的参数回落到配置文件/环境变量，再回落到内置默认值（见 config.rs）
+#[derive(Parser, Debug)]
+#[command(name = "cekproxy", version, about = "Cloudflare proxyIP scanner with GeoIP enrichment and PostgreSQL sync")]
+pub struct Cli {
+    /// Config file to load (default: cekproxy.toml if present, or $CEKPROXY_CONFIG)
+    #[arg(long, global = true)]
+    pub config: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Scan a proxy list and write the live proxies to the output file
+    Scan(Box<ScanArgs>),
+    /// Check a single proxy and print the result
+    Check(CheckArgs),
+    /// Print GeoIP/ASN and blocklist info for IPs without probing them
+    Enrich(EnrichArgs),
+    /// PostgreSQL maintenance commands
+    Db(DbArgs),
+}
+
+impl Cli {
+    // 未指定子命令时按默认参数执行 scan，兼容旧的无参数调用方式
+    pub fn into_command(self) -> Command {
+        match self.command {
+            Some(command) => command,
+            None => match Cli::parse_from(["cekproxy", "scan"]).command {
+                Some(command) => command,
+                None => unreachable!("scan subcommand always parses"),
+            },
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct ScanArgs {
+    /// Proxy lists or globs to scan; '-' reads stdin, streams results to stdout as JSON Lines unless --stdout is given and skips the default CSV file, report and database sync unless they are configured
+    #[arg(value_name = "INPUT")]
+    pub inputs: Vec<String>,
+
+    /// Proxy list or glob to scan (ip:port, [ipv6]:port, bare ip, CSV or JSON Lines), repeatable; duplicates are merged [default: Data/emeliaProxyIP15AGS.txt]
+    #[arg(short, long)]
+    pub input: Vec<String>,
+
+    /// Ports to check for input lines that only contain an IP or CIDR, comma-separated [default: 443]
+    #[arg(long, value_delimiter = ',')]
+    pub ports: Vec<u16>,
+
+    /// Expand IP and CIDR inputs to the Cloudflare HTTPS ports 443, 2053, 2083, 2087, 2096 and 8443
+    #[arg(long, conflicts_with = "ports")]
+    pub cf_ports: bool,
+
+    /// Do not deduplicate (ip, port) across inputs; saves memory on very large candidate sets
+    #[arg(long)]
+    pub no_dedup: bool,
+
+    /// File of IPs and CIDRs that must never be contacted, one per line, repeatable
+    #[arg(long, value_name = "FILE")]
+    pub exclude: Vec<String>,
+
+    /// Also scan private, loopback, multicast and documentation ranges
+    #[arg(long)]
+    pub no_exclude_reserved: bool,
+
+    /// CSV file that receives the live proxies [default: Data/alive.txt]
+    #[arg(short, long)]
+    pub output: Option<String>,
+
+    /// Do not write the CSV output file
+    #[arg(long, conflicts_with = "output")]
+    pub no_csv: bool,
+
+    /// Omit the header row from CSV output
+    #[arg(long)]
+    pub no_csv_header: bool,
+
+    /// Also write results as JSON Lines to this file
+    #[arg(long)]
+    pub jsonl: Option<String>,
+
+    /// Also write results as tab-separated values to this file, same columns as the CSV
+    #[arg(long)]
+    pub tsv: Option<String>,
+
+    /// Stream results to stdout in the given format
+    #[arg(long, value_enum)]
+    pub stdout: Option<OutputFormat>,
+
+    /// Write every proxy that failed a check to this CSV file with the reason
+    #[arg(long)]
+    pub rejected: Option<String>,
+
+    /// Write the end-of-run summary as JSON to this file [default: Data/scan_report.json]
+    #[arg(long)]
+    pub report: Option<String>,
+
+    /// Do not write the JSON scan report
+    #[arg(long, conflicts_with = "report")]
+    pub no_report: bool,
+
+    /// Number of proxies checked in parallel [default: 175]
+    #[arg(short = 'c', long)]
+    pub max_concurrent: Option<usize>,
+
+    /// Adjust concurrency between --min-concurrent and --max-concurrent based on the timeout rate
+    #[arg(long)]
+    pub adaptive: bool,
+
+    /// Lower bound for adaptive concurrency [default: 16]
+    #[arg(long)]
+    pub min_concurrent: Option<usize>,
+
+    /// Maximum new proxy connections per second across the whole scan, 0 for unlimited [default: 0]
+    #[arg(long)]
+    pub rate_limit: Option<f64>,
+
+    /// Maximum parallel checks against one IP (any port), 0 for unlimited [default: 2]
+    #[arg(long)]
+    pub per_ip: Option<usize>,
+
+    /// Maximum parallel checks within one /24 (IPv6 /48), 0 for unlimited [default: 16]
+    #[arg(long)]
+    pub per_subnet: Option<usize>,
+
+    /// Do not show the live progress line
+    #[arg(long)]
+    pub no_progress: bool,
+
+    /// Skip PostgreSQL sync and only write the output file
+    #[arg(long)]
+    pub no_db: bool,
+
+    #[command(flatten)]
+    pub probe: ProbeArgs,
+
+    #[command(flatten)]
+    pub data: DataArgs,
+
+    #[command(flatten)]
+    pub database: DatabaseArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct CheckArgs {
+    /// Proxy to check, as ip:port, [ipv6]:port or ip,port
+    pub proxy: String,
+
+    #[command(flatten)]
+    pub probe: ProbeArgs,
+
+    #[command(flatten)]
+    pub data: DataArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct EnrichArgs {
+    /// IP addresses to look up
+    pub ips: Vec<String>,
+
+    /// Read IPs from a proxy list in any format accepted by scan --input
+    #[arg(short, long)]
+    pub input: Option<String>,
+
+    #[command(flatten)]
+    pub data: DataArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct DbArgs {
+    #[command(subcommand)]
+    pub command: DbCommand,
+
+    #[command(flatten)]
+    pub database: DatabaseArgs,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum DbCommand {
+    /// Test the connection and show the current proxy count
+    Status,
+    /// Add the columns newer versions write to an existing proxies table
+    Migrate,
+    /// Delete proxies that were not refreshed within the given number of hours
+    Cleanup {
+        #[arg(long, default_value_t = 24)]
+        older_than_hours: i64,
+    },
+}
+
+// IP 回显服务与超时设置
+#[derive(Args, Debug, Clone)]
+pub struct ProbeArgs {
+    /// IP echo endpoint as [format:]host[/path] (meta, trace, json or plain), repeatable; e.g. trace:example.com [default: meta:speed.cloudflare.com/meta]
+    #[arg(long = "resolver")]
+    pub resolver: Vec<ResolverConfig>,
+
+    /// How many resolvers each proxy is checked against, 0 for all [default: 1]
+    #[arg(long)]
+    pub resolvers_per_check: Option<usize>,
+
+    /// How many resolvers must see the same exit IP for a proxy to count as live [default: 1]
+    #[arg(long)]
+    pub quorum: Option<usize>,
+
+    /// TCP connect timeout in milliseconds [default: 2000]
+    #[arg(long)]
+    pub connect_timeout: Option<u64>,
+
+    /// TLS handshake timeout in milliseconds [default: 3000]
+    #[arg(long)]
+    pub handshake_timeout: Option<u64>,
+
+    /// Time to wait for the first response byte after sending the request, in milliseconds [default: 5000]
+    #[arg(long)]
+    pub first_byte_timeout: Option<u64>,
+
+    /// Timeout for a whole proxy check, in seconds [default: 9]
+    #[arg(short, long)]
+    pub timeout: Option<u64>,
+
+    /// Probe ports expanded from the port set directly with TLS, without the TCP pre-check
+    #[arg(long)]
+    pub no_tcp_precheck: bool,
+
+    /// TCP pre-check connect timeout in milliseconds [default: 1000]
+    #[arg(long)]
+    pub precheck_timeout: Option<u64>,
+
+    /// TLS server name (SNI) to send, independent of the Host header [default: resolver host]
+    #[arg(long)]
+    pub sni: Option<String>,
+
+    /// PEM file with extra root certificates to trust
+    #[arg(long)]
+    pub ca_bundle: Option<String>,
+
+    /// Trust only the certificates in --ca-bundle, not the system roots
+    #[arg(long, requires = "ca_bundle")]
+    pub no_builtin_roots: bool,
+
+    /// Skip certificate and hostname verification (diagnostics only)
+    #[arg(long)]
+    pub insecure: bool,
+
+    /// Attempts per resolver for retryable failures, 1 disables retries [default: 2]
+    #[arg(long)]
+    pub max_attempts: Option<u32>,
+
+    /// Error class to retry on, repeatable [default: tls_handshake_timeout, first_byte_timeout, read_timeout, connection_reset]
+    #[arg(long)]
+    pub retry_on: Vec<String>,
+}
+
+// GeoIP 数据库与黑名单文件路径
+#[derive(Args, Debug, Clone)]
+pub struct DataArgs {
+    /// MaxMind Country database (required) [default: Data/GeoLite2-Country.mmdb]
+    #[arg(long)]
+    pub country_db: Option<String>,
+
+    /// MaxMind City database (optional) [default: Data/GeoLite2-City.mmdb]
+    #[arg(long)]
+    pub city_db: Option<String>,
+
+    /// MaxMind ASN database (optional) [default: Data/GeoLite2-ASN.mmdb]
+    #[arg(long)]
+    pub asn_db: Option<String>,
+
+    /// MaxMind Anonymous IP database used to drop VPN/proxy/Tor IPs (optional) [default: Data/GeoIP2-Anonymous-IP.mmdb]
+    #[arg(long)]
+    pub anonymous_db: Option<String>,
+
+    /// AbuseIPDB blacklist (ip,country_code,score per line) [default: Data/abuseips.txt]
+    #[arg(long)]
+    pub abuse_ips: Option<String>,
+
+    /// FireHOL CIDR blocklist (one CIDR per line) [default: Data/firehol_cidr.txt]
+    #[arg(long)]
+    pub firehol_cidrs: Option<String>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct DatabaseArgs {
+    /// PostgreSQL connection string (defaults to the DATABASE_URL environment variable)
+    #[arg(long)]
+    pub database_url: Option<String>,
+}
@@ -0,0 +1,172 @@
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::config::ScanConfig;
+use crate::error::CheckError;
+
+// 每个评估窗口至少包含的完成数，避免样本太少导致来回抖动
+const MIN_WINDOW: usize = 32;
+// 加性增长的步长与乘性下降的系数
+const INCREASE_STEP: usize = 4;
+const DECREASE_FACTOR: f64 = 0.75;
+// 基线缓慢向上跟随当前错误率，适应输入列表中死代理比例的变化
+const BASELINE_DRIFT: f64 = 0.05;
+
+// 并发控制：固定模式下就是 max_concurrent 个许可；
+// 自适应模式 (AIMD) 从 min_concurrent 开始，超时/重置比例不高于基线 + tolerance 时逐步增加，
+// 超过时按比例减少，遇到本机资源耗尽（文件描述符、端口）立即减少
+pub struct ConcurrencyLimiter {
+    semaphore: Arc<Semaphore>,
+    adaptive: bool,
+    min: usize,
+    max: usize,
+    tolerance: f64,
+    state: Mutex<State>,
+}
+
+struct State {
+    limit: usize,
+    // 下调后尚未收回的许可数，归还时直接丢弃
+    excess: usize,
+    window_total: usize,
+    window_pressure: usize,
+    // 观察到的最低超时比例（死代理本身就会超时，只有高出基线的部分才算压力）
+    baseline: Option<f64>,
+}
+
+// 持有期间占用一个并发名额
+pub struct Permit {
+    permit: Option<OwnedSemaphorePermit>,
+    limiter: Arc<ConcurrencyLimiter>,
+}
+
+impl ConcurrencyLimiter {
+    pub fn new(config: &ScanConfig) -> Arc<Self> {
+        let max = config.max_concurrent.max(1);
+        let min = config.min_concurrent.clamp(1, max);
+        let initial = if config.adaptive { min } else { max };
+
+        Arc::new(ConcurrencyLimiter {
+            semaphore: Arc::new(Semaphore::new(initial)),
+            adaptive: config.adaptive,
+            min,
+            max,
+            tolerance: config.adaptive_tolerance,
+            state: Mutex::new(State {
+                limit: initial,
+                excess: 0,
+                window_total: 0,
+                window_pressure: 0,
+                baseline: None,
+            }),
+        })
+    }
+
+    pub fn is_adaptive(&self) -> bool {
+        self.adaptive
+    }
+
+    // 当前允许的最大并发数
+    pub fn limit(&self) -> usize {
+        self.state.lock().unwrap().limit
+    }
+
+    pub async fn acquire(self: &Arc<Self>) -> Permit {
+        let permit = Arc::clone(&self.semaphore)
+            .acquire_owned()
+            .await
+            .expect("concurrency semaphore is never closed");
+        Permit {
+            permit: Some(permit),
+            limiter: Arc::clone(self),
+        }
+    }
+
+    // 记录一次检测结果，error 为 None 表示存活
+    pub fn record(&self, error: Option<&CheckError>) {
+        if !self.adaptive {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+
+        if let Some(CheckError::LocalResource(_)) = error {
+            self.decrease(&mut state);
+            return;
+        }
+
+        state.window_total += 1;
+        if error.is_some_and(is_pressure) {
+            state.window_pressure += 1;
+        }
+
+        if state.window_total < state.limit.max(MIN_WINDOW) {
+            return;
+        }
+
+        let rate = state.window_pressure as f64 / state.window_total as f64;
+        let baseline = match state.baseline {
+            Some(baseline) if rate >= baseline => baseline + (rate - baseline) * BASELINE_DRIFT,
+            _ => rate,
+        };
+        state.baseline = Some(baseline);
+
+        if rate > baseline + self.tolerance {
+            self.decrease(&mut state);
+        } else {
+            self.increase(&mut state);
+        }
+    }
+
+    fn increase(&self, state: &mut State) {
+        let new_limit = (state.limit + INCREASE_STEP).min(self.max);
+        let mut added = new_limit - state.limit;
+        state.limit = new_limit;
+        reset_window(state);
+
+        // 先抵消尚未收回的许可，剩余部分才真正加入信号量
+        let cancelled = added.min(state.excess);
+        state.excess -= cancelled;
+        added -= cancelled;
+        if added > 0 {
+            self.semaphore.add_permits(added);
+        }
+    }
+
+    fn decrease(&self, state: &mut State) {
+        let new_limit = ((state.limit as f64 * DECREASE_FACTOR) as usize).max(self.min);
+        state.excess += state.limit - new_limit;
+        state.limit = new_limit;
+        reset_window(state);
+    }
+}
+
+fn reset_window(state: &mut State) {
+    state.window_total = 0;
+    state.window_pressure = 0;
+}
+
+// 可能由本机或网络拥塞引起的失败；connect_refused 等明确的拒绝不算
+fn is_pressure(error: &CheckError) -> bool {
+    matches!(
+        error,
+        CheckError::ConnectTimeout
+            | CheckError::TlsHandshakeTimeout
+            | CheckError::FirstByteTimeout
+            | CheckError::ReadTimeout
+            | CheckError::ConnectionReset
+    )
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let mut state = self.limiter.state.lock().unwrap();
+        if state.excess > 0 {
+            state.excess -= 1;
+            if let Some(permit) = self.permit.take() {
+                permit.forget();
+            }
+        }
+    }
+}
@@ -0,0 +1,687 @@
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer};
+
+use crate::cli::{DataArgs, DatabaseArgs, ProbeArgs, ScanArgs};
+use crate::input::STDIN;
+use crate::output::OutputFormat;
+use crate::Result;
+
+// Cloudflare 支持 HTTPS 的端口，--cf-ports 使用这组端口展开只有 IP/网段的输入
+pub const CF_HTTPS_PORTS: &[u16] = &[443, 2053, 2083, 2087, 2096, 8443];
+
+// 未通过 --config 指定时，若当前目录存在该文件则自动加载
+const DEFAULT_CONFIG_FILE: &str = "cekproxy.toml";
+
+// 配置分层：内置默认值 < cekproxy.toml < CEKPROXY_* 环境变量 < 命令行参数
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub scan: ScanConfig,
+    pub output: OutputConfig,
+    pub probe: ProbeConfig,
+    pub tls: TlsConfig,
+    pub retry: RetryConfig,
+    pub data: DataConfig,
+    pub filters: FilterConfig,
+    pub database: DatabaseConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScanConfig {
+    // 输入文件或 glob 模式，多个文件按 (ip, port) 去重后合并
+    #[serde(deserialize_with = "string_or_list")]
+    pub input: Vec<String>,
+    // 输入行只有 IP 或网段时检测的端口
+    pub ports: Vec<u16>,
+    // 按 (ip, port) 去重，已输出的地址按区间记录；千万级输入可关闭以节省内存
+    pub dedup: bool,
+    // 排除列表文件（每行一个 IP 或 CIDR），命中的地址不会被连接
+    pub exclude: Vec<String>,
+    // 跳过私有、回环、文档示例等保留网段
+    pub exclude_reserved: bool,
+    // 固定模式下的并发数；adaptive = true 时为上限，min_concurrent 为下限
+    pub max_concurrent: usize,
+    pub adaptive: bool,
+    pub min_concurrent: usize,
+    // 超时/重置比例高出基线多少时开始降低并发
+    pub adaptive_tolerance: f64,
+    // 全局建立连接的速率（每秒），0 表示不限速
+    pub rate_limit: f64,
+    // 同一 IP、同一 /24 网段同时进行的检测数上限，0 表示不限制
+    pub per_ip_concurrent: usize,
+    pub per_subnet_concurrent: usize,
+    // 显示扫描进度：终端上原地刷新，CI 日志中定期打印一行
+    pub progress: bool,
+}
+
+impl Default for ScanConfig {
+    fn default() -> Self {
+        ScanConfig {
+            input: vec!["Data/emeliaProxyIP15AGS.txt".to_string()],
+            ports: vec![443],
+            dedup: true,
+            exclude: Vec::new(),
+            exclude_reserved: true,
+            max_concurrent: 175,
+            adaptive: false,
+            min_concurrent: 16,
+            adaptive_tolerance: 0.15,
+            rate_limit: 0.0,
+            per_ip_concurrent: 2,
+            per_subnet_concurrent: 16,
+            progress: true,
+        }
+    }
+}
+
+// 结果输出目标，可同时启用多个；csv 设为空字符串即关闭 CSV 文件输出
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputConfig {
+    pub csv: Option<String>,
+    pub csv_header: bool,
+    pub jsonl: Option<String>,
+    // TSV 与 CSV 列相同，字段中的制表符与换行替换为空格
+    pub tsv: Option<String>,
+    pub stdout: Option<OutputFormat>,
+    pub rejected: Option<String>,
+    pub report: Option<String>,
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        OutputConfig {
+            csv: Some("Data/alive.txt".to_string()),
+            csv_header: true,
+            jsonl: None,
+            tsv: None,
+            stdout: None,
+            rejected: None,
+            report: Some("Data/scan_report.json".to_string()),
+        }
+    }
+}
+
+// 每个代理会轮流使用 resolvers 中的 resolvers_per_check 个回显服务检测，
+// 至少 quorum 个返回相同的出口 IP 才判定为存活
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProbeConfig {
+    pub resolvers: Vec<ResolverConfig>,
+    // 0 表示每次使用全部 resolvers
+    pub resolvers_per_check: usize,
+    pub quorum: usize,
+    // 各阶段超时（毫秒）与整次检测的总超时（秒）
+    pub connect_timeout_ms: u64,
+    pub handshake_timeout_ms: u64,
+    pub first_byte_timeout_ms: u64,
+    pub timeout_seconds: u64,
+    // 响应体上限，超过即判定失败，防止异常代理持续推送数据
+    pub max_body_bytes: usize,
+    // 从端口集合展开的地址先做一次 TCP 连接预检，端口未开放时不再进行完整检测
+    pub tcp_precheck: bool,
+    pub precheck_timeout_ms: u64,
+}
+
+impl Default for ProbeConfig {
+    fn default() -> Self {
+        ProbeConfig {
+            resolvers: vec![ResolverConfig {
+                host: "speed.cloudflare.com".to_string(),
+                path: "/meta".to_string(),
+                format: ResponseFormat::Meta,
+                ip_field: None,
+            }],
+            resolvers_per_check: 1,
+            quorum: 1,
+            connect_timeout_ms: 2000,
+            handshake_timeout_ms: 3000,
+            first_byte_timeout_ms: 5000,
+            timeout_seconds: 9,
+            max_body_bytes: 64 * 1024,
+            tcp_precheck: true,
+            precheck_timeout_ms: 1000,
+        }
+    }
+}
+
+// IP 回显服务，format 决定响应的解析方式
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ResolverConfig {
+    pub host: String,
+    #[serde(default = "default_resolver_path")]
+    pub path: String,
+    #[serde(default)]
+    pub format: ResponseFormat,
+    // format = "json" 时读取出口 IP 的字段，默认 "ip"
+    pub ip_field: Option<String>,
+}
+
+fn default_resolver_path() -> String {
+    "/".to_string()
+}
+
+// 命令行与环境变量中的简写: [format:]host[/path]，例如 plain:icanhazip.com/
+// 省略 path 时使用该格式的默认路径，例如 trace:example.com 即 example.com/cdn-cgi/trace
+impl FromStr for ResolverConfig {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (format, target) = match s.split_once(':') {
+            Some((format, target)) => (format.parse()?, target),
+            None => (ResponseFormat::Meta, s),
+        };
+        let (host, path) = match target.find('/') {
+            Some(index) => (&target[..index], target[index..].to_string()),
+            None => (target, format.default_path().to_string()),
+        };
+        if host.is_empty() {
+            return Err(format!("resolver '{}' has no host", s));
+        }
+        Ok(ResolverConfig {
+            host: host.to_string(),
+            path,
+            format,
+            ip_field: None,
+        })
+    }
+}
+
+impl fmt::Display for ResolverConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.host, self.path)
+    }
+}
+
+// meta: speed.cloudflare.com/meta 的 JSON；trace: Cloudflare /cdn-cgi/trace 的 key=value 文本
+// json: 任意 JSON 回显；plain: 响应体即 IP
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResponseFormat {
+    #[default]
+    Meta,
+    Trace,
+    Json,
+    Plain,
+}
+
+impl ResponseFormat {
+    fn default_path(self) -> &'static str {
+        match self {
+            ResponseFormat::Meta => "/meta",
+            ResponseFormat::Trace => "/cdn-cgi/trace",
+            ResponseFormat::Json | ResponseFormat::Plain => "/",
+        }
+    }
+}
+
+impl FromStr for ResponseFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "meta" => Ok(ResponseFormat::Meta),
+            "trace" => Ok(ResponseFormat::Trace),
+            "json" => Ok(ResponseFormat::Json),
+            "plain" => Ok(ResponseFormat::Plain),
+            _ => Err(format!("unknown resolver format '{}', expected meta, trace, json or plain", s)),
+        }
+    }
+}
+
+// TLS 客户端设置；sni 未设置时使用各 resolver 的 host
+// ca_bundle 为 PEM 文件，可包含多个证书；builtin_roots = false 时只信任 ca_bundle
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsConfig {
+    pub sni: Option<String>,
+    pub ca_bundle: Option<String>,
+    pub builtin_roots: bool,
+    // 跳过证书与主机名校验，仅用于排查问题
+    pub insecure: bool,
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        TlsConfig {
+            sni: None,
+            ca_bundle: None,
+            builtin_roots: true,
+            insecure: false,
+        }
+    }
+}
+
+// 超时、连接重置等偶发错误的重试策略，max_attempts = 1 即不重试
+// retry_on 使用 CheckError::kind 的标签；connect_timeout 默认不重试，因为大多数死代理都是连接超时
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryConfig {
+    pub max_attempts: u32,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+    pub retry_on: Vec<String>,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        RetryConfig {
+            max_attempts: 2,
+            base_delay_ms: 500,
+            max_delay_ms: 4000,
+            retry_on: vec![
+                "tls_handshake_timeout".to_string(),
+                "first_byte_timeout".to_string(),
+                "read_timeout".to_string(),
+                "connection_reset".to_string(),
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DataConfig {
+    pub country_db: String,
+    pub city_db: String,
+    pub asn_db: String,
+    pub anonymous_db: String,
+    pub abuse_ips: String,
+    pub firehol_cidrs: String,
+}
+
+impl Default for DataConfig {
+    fn default() -> Self {
+        DataConfig {
+            country_db: "Data/GeoLite2-Country.mmdb".to_string(),
+            city_db: "Data/GeoLite2-City.mmdb".to_string(),
+            asn_db: "Data/GeoLite2-ASN.mmdb".to_string(),
+            anonymous_db: "Data/GeoIP2-Anonymous-IP.mmdb".to_string(),
+            abuse_ips: "Data/abuseips.txt".to_string(),
+            firehol_cidrs: "Data/firehol_cidr.txt".to_string(),
+        }
+    }
+}
+
+// 黑名单过滤开关，关闭后对应数据不会被加载
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FilterConfig {
+    pub anonymous: bool,
+    pub abuse_ips: bool,
+    pub firehol: bool,
+    pub target: FilterTarget,
+}
+
+impl Default for FilterConfig {
+    fn default() -> Self {
+        FilterConfig {
+            anonymous: true,
+            abuse_ips: true,
+            firehol: true,
+            target: FilterTarget::Both,
+        }
+    }
+}
+
+// 黑名单作用于哪个地址：入口 IP（输入列表中的 IP）、出口 IP（Cloudflare 看到的 clientIp）或两者
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FilterTarget {
+    Entry,
+    Exit,
+    Both,
+}
+
+impl FromStr for FilterTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "entry" => Ok(FilterTarget::Entry),
+            "exit" => Ok(FilterTarget::Exit),
+            "both" => Ok(FilterTarget::Both),
+            _ => Err(format!("unknown filter target '{}', expected entry, exit or both", s)),
+        }
+    }
+}
+
+// enabled = false 时扫描结果只写入输出文件，不连接 PostgreSQL
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub enabled: bool,
+    pub url: Option<String>,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        DatabaseConfig {
+            enabled: true,
+            url: None,
+        }
+    }
+}
+
+impl Config {
+    // 读取配置文件并应用环境变量覆盖
+    // 显式指定的配置文件不存在时报错，默认配置文件不存在则使用内置默认值
+    pub fn load(path: Option<&str>) -> Result<Self> {
+        let path = path
+            .map(str::to_string)
+            .or_else(|| env::var("CEKPROXY_CONFIG").ok());
+
+        let mut config = match path {
+            Some(path) => Self::from_file(&path)?,
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => Self::from_file(DEFAULT_CONFIG_FILE)?,
+            None => Config::default(),
+        };
+
+        config.apply_env()?;
+        Ok(config)
+    }
+
+    fn from_file(path: &str) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read config file {}: {}", path, e))?;
+        let config = toml::from_str(&content)
+            .map_err(|e| format!("Invalid config file {}: {}", path, e))?;
+        eprintln!("Loaded config file: {}", path);
+        Ok(config)
+    }
+
+    fn apply_env(&mut self) -> Result<()> {
+        env_override_list("CEKPROXY_INPUT", &mut self.scan.input)?;
+        env_override_list("CEKPROXY_PORTS", &mut self.scan.ports)?;
+        env_override("CEKPROXY_DEDUP", &mut self.scan.dedup)?;
+        env_override_list("CEKPROXY_EXCLUDE", &mut self.scan.exclude)?;
+        env_override("CEKPROXY_EXCLUDE_RESERVED", &mut self.scan.exclude_reserved)?;
+        env_override("CEKPROXY_MAX_CONCURRENT", &mut self.scan.max_concurrent)?;
+        env_override("CEKPROXY_ADAPTIVE", &mut self.scan.adaptive)?;
+        env_override("CEKPROXY_MIN_CONCURRENT", &mut self.scan.min_concurrent)?;
+        env_override("CEKPROXY_ADAPTIVE_TOLERANCE", &mut self.scan.adaptive_tolerance)?;
+        env_override("CEKPROXY_RATE_LIMIT", &mut self.scan.rate_limit)?;
+        env_override("CEKPROXY_PER_IP_CONCURRENT", &mut self.scan.per_ip_concurrent)?;
+        env_override("CEKPROXY_PER_SUBNET_CONCURRENT", &mut self.scan.per_subnet_concurrent)?;
+        env_override("CEKPROXY_PROGRESS", &mut self.scan.progress)?;
+
+        env_override_opt("CEKPROXY_OUTPUT", &mut self.output.csv)?;
+        env_override("CEKPROXY_CSV_HEADER", &mut self.output.csv_header)?;
+        env_override_opt("CEKPROXY_JSONL_OUTPUT", &mut self.output.jsonl)?;
+        env_override_opt("CEKPROXY_TSV_OUTPUT", &mut self.output.tsv)?;
+        env_override_opt("CEKPROXY_STDOUT_FORMAT", &mut self.output.stdout)?;
+        env_override_opt("CEKPROXY_REJECTED_OUTPUT", &mut self.output.rejected)?;
+        env_override_opt("CEKPROXY_REPORT_OUTPUT", &mut self.output.report)?;
+
+        env_override_list("CEKPROXY_RESOLVERS", &mut self.probe.resolvers)?;
+        env_override("CEKPROXY_RESOLVERS_PER_CHECK", &mut self.probe.resolvers_per_check)?;
+        env_override("CEKPROXY_QUORUM", &mut self.probe.quorum)?;
+        env_override("CEKPROXY_CONNECT_TIMEOUT_MS", &mut self.probe.connect_timeout_ms)?;
+        env_override("CEKPROXY_HANDSHAKE_TIMEOUT_MS", &mut self.probe.handshake_timeout_ms)?;
+        env_override("CEKPROXY_FIRST_BYTE_TIMEOUT_MS", &mut self.probe.first_byte_timeout_ms)?;
+        env_override("CEKPROXY_TIMEOUT_SECONDS", &mut self.probe.timeout_seconds)?;
+        env_override("CEKPROXY_MAX_BODY_BYTES", &mut self.probe.max_body_bytes)?;
+        env_override("CEKPROXY_TCP_PRECHECK", &mut self.probe.tcp_precheck)?;
+        env_override("CEKPROXY_PRECHECK_TIMEOUT_MS", &mut self.probe.precheck_timeout_ms)?;
+
+        env_override_opt("CEKPROXY_TLS_SNI", &mut self.tls.sni)?;
+        env_override_opt("CEKPROXY_TLS_CA_BUNDLE", &mut self.tls.ca_bundle)?;
+        env_override("CEKPROXY_TLS_BUILTIN_ROOTS", &mut self.tls.builtin_roots)?;
+        env_override("CEKPROXY_TLS_INSECURE", &mut self.tls.insecure)?;
+
+        env_override("CEKPROXY_RETRY_MAX_ATTEMPTS", &mut self.retry.max_attempts)?;
+        env_override("CEKPROXY_RETRY_BASE_DELAY_MS", &mut self.retry.base_delay_ms)?;
+        env_override("CEKPROXY_RETRY_MAX_DELAY_MS", &mut self.retry.max_delay_ms)?;
+        env_override_list("CEKPROXY_RETRY_ON", &mut self.retry.retry_on)?;
+
+        env_override("CEKPROXY_COUNTRY_DB", &mut self.data.country_db)?;
+        env_override("CEKPROXY_CITY_DB", &mut self.data.city_db)?;
+        env_override("CEKPROXY_ASN_DB", &mut self.data.asn_db)?;
+        env_override("CEKPROXY_ANONYMOUS_DB", &mut self.data.anonymous_db)?;
+        env_override("CEKPROXY_ABUSE_IPS", &mut self.data.abuse_ips)?;
+        env_override("CEKPROXY_FIREHOL_CIDRS", &mut self.data.firehol_cidrs)?;
+
+        env_override("CEKPROXY_FILTER_ANONYMOUS", &mut self.filters.anonymous)?;
+        env_override("CEKPROXY_FILTER_ABUSE_IPS", &mut self.filters.abuse_ips)?;
+        env_override("CEKPROXY_FILTER_FIREHOL", &mut self.filters.firehol)?;
+        env_override("CEKPROXY_FILTER_TARGET", &mut self.filters.target)?;
+
+        env_override("CEKPROXY_DB_ENABLED", &mut self.database.enabled)?;
+
+        // DATABASE_URL 保持原有的兼容行为，CEKPROXY_DATABASE_URL 优先级更高
+        if let Ok(url) = env::var("DATABASE_URL") {
+            self.database.url = Some(url);
+        }
+        if let Ok(url) = env::var("CEKPROXY_DATABASE_URL") {
+            self.database.url = Some(url);
+        }
+
+        // 空字符串表示关闭 CSV 文件输出
+        if self.output.csv.as_deref() == Some("") {
+            self.output.csv = None;
+        }
+        if self.output.report.as_deref() == Some("") {
+            self.output.report = None;
+        }
+
+        Ok(())
+    }
+
+    // 合并完所有来源后检查扫描参数，避免无效值到扫描开始后才表现为卡住
+    pub fn validate_scan(&self) -> Result<()> {
+        if self.scan.max_concurrent == 0 {
+            return Err("max_concurrent must be at least 1 (--max-concurrent / CEKPROXY_MAX_CONCURRENT / [scan] max_concurrent)".into());
+        }
+        Ok(())
+    }
+
+    pub fn apply_scan_args(&mut self, args: &ScanArgs) {
+        if !args.inputs.is_empty() || !args.input.is_empty() {
+            self.scan.input = args.inputs.iter().chain(&args.input).cloned().collect();
+        }
+        if !args.ports.is_empty() {
+            self.scan.ports = args.ports.clone();
+        }
+        if args.cf_ports {
+            self.scan.ports = CF_HTTPS_PORTS.to_vec();
+        }
+        if !args.exclude.is_empty() {
+            self.scan.exclude = args.exclude.clone();
+        }
+        if args.no_exclude_reserved {
+            self.scan.exclude_reserved = false;
+        }
+        override_with(&mut self.scan.max_concurrent, &args.max_concurrent);
+        override_with(&mut self.scan.min_concurrent, &args.min_concurrent);
+        override_with(&mut self.scan.rate_limit, &args.rate_limit);
+        override_with(&mut self.scan.per_ip_concurrent, &args.per_ip);
+        override_with(&mut self.scan.per_subnet_concurrent, &args.per_subnet);
+        if args.adaptive {
+            self.scan.adaptive = true;
+        }
+        if args.no_dedup {
+            self.scan.dedup = false;
+        }
+        if args.no_progress {
+            self.scan.progress = false;
+        }
+        if args.output.is_some() {
+            self.output.csv = args.output.clone();
+        }
+        if args.no_csv {
+            self.output.csv = None;
+        }
+        if args.no_csv_header {
+            self.output.csv_header = false;
+        }
+        if args.jsonl.is_some() {
+            self.output.jsonl = args.jsonl.clone();
+        }
+        if args.tsv.is_some() {
+            self.output.tsv = args.tsv.clone();
+        }
+        if args.stdout.is_some() {
+            self.output.stdout = args.stdout;
+        }
+        if args.rejected.is_some() {
+            self.output.rejected = args.rejected.clone();
+        }
+        if args.report.is_some() {
+            self.output.report = args.report.clone();
+        }
+        if args.no_report {
+            self.output.report = None;
+        }
+        if args.no_db {
+            self.database.enabled = false;
+        }
+        self.apply_probe_args(&args.probe);
+        self.apply_data_args(&args.data);
+        self.apply_database_args(&args.database);
+
+        // scan - 用于管道：从 stdin 读取候选，结果写到 stdout（日志始终在 stderr）
+        // 没有另行配置时不写默认的 CSV 与报告文件，没有连接串时不连接数据库
+        if self.scan.input.iter().any(|input| input == STDIN) {
+            let defaults = OutputConfig::default();
+            if self.output.stdout.is_none() {
+                self.output.stdout = Some(OutputFormat::Jsonl);
+            }
+            if args.output.is_none() && self.output.csv == defaults.csv {
+                self.output.csv = None;
+            }
+            if args.report.is_none() && self.output.report == defaults.report {
+                self.output.report = None;
+            }
+            if self.database.url.is_none() {
+                self.database.enabled = false;
+            }
+        }
+    }
+
+    pub fn apply_probe_args(&mut self, args: &ProbeArgs) {
+        if !args.resolver.is_empty() {
+            self.probe.resolvers = args.resolver.clone();
+        }
+        override_with(&mut self.probe.resolvers_per_check, &args.resolvers_per_check);
+        override_with(&mut self.probe.quorum, &args.quorum);
+        override_with(&mut self.probe.connect_timeout_ms, &args.connect_timeout);
+        override_with(&mut self.probe.handshake_timeout_ms, &args.handshake_timeout);
+        override_with(&mut self.probe.first_byte_timeout_ms, &args.first_byte_timeout);
+        override_with(&mut self.probe.timeout_seconds, &args.timeout);
+        if args.no_tcp_precheck {
+            self.probe.tcp_precheck = false;
+        }
+        override_with(&mut self.probe.precheck_timeout_ms, &args.precheck_timeout);
+        if args.sni.is_some() {
+            self.tls.sni = args.sni.clone();
+        }
+        if args.ca_bundle.is_some() {
+            self.tls.ca_bundle = args.ca_bundle.clone();
+        }
+        if args.no_builtin_roots {
+            self.tls.builtin_roots = false;
+        }
+        if args.insecure {
+            self.tls.insecure = true;
+        }
+        override_with(&mut self.retry.max_attempts, &args.max_attempts);
+        if !args.retry_on.is_empty() {
+            self.retry.retry_on = args.retry_on.clone();
+        }
+    }
+
+    pub fn apply_data_args(&mut self, args: &DataArgs) {
+        override_with(&mut self.data.country_db, &args.country_db);
+        override_with(&mut self.data.city_db, &args.city_db);
+        override_with(&mut self.data.asn_db, &args.asn_db);
+        override_with(&mut self.data.anonymous_db, &args.anonymous_db);
+        override_with(&mut self.data.abuse_ips, &args.abuse_ips);
+        override_with(&mut self.data.firehol_cidrs, &args.firehol_cidrs);
+    }
+
+    pub fn apply_database_args(&mut self, args: &DatabaseArgs) {
+        if let Some(url) = &args.database_url {
+            self.database.url = Some(url.clone());
+        }
+    }
+}
+
+// 环境变量存在时解析并覆盖，解析失败直接报错而不是静默忽略
+fn env_override<T: FromStr>(name: &str, target: &mut T) -> Result<()>
+where
+    T::Err: std::fmt::Display,
+{
+    if let Ok(value) = env::var(name) {
+        *target = value
+            .trim()
+            .parse()
+            .map_err(|e| format!("Invalid value for {}: '{}' ({})", name, value, e))?;
+    }
+    Ok(())
+}
+
+// 可选项：空字符串表示关闭
+fn env_override_opt<T: FromStr>(name: &str, target: &mut Option<T>) -> Result<()>
+where
+    T::Err: std::fmt::Display,
+{
+    if let Ok(value) = env::var(name) {
+        *target = if value.trim().is_empty() {
+            None
+        } else {
+            let parsed = value
+                .trim()
+                .parse()
+                .map_err(|e| format!("Invalid value for {}: '{}' ({})", name, value, e))?;
+            Some(parsed)
+        };
+    }
+    Ok(())
+}
+
+// 逗号分隔的列表，整体替换配置文件中的值
+fn env_override_list<T: FromStr>(name: &str, target: &mut Vec<T>) -> Result<()>
+where
+    T::Err: std::fmt::Display,
+{
+    if let Ok(value) = env::var(name) {
+        *target = value
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(|item| item.parse().map_err(|e| format!("Invalid value for {}: '{}' ({})", name, item, e)))
+            .collect::<std::result::Result<_, _>>()?;
+    }
+    Ok(())
+}
+
+fn override_with<T: Clone>(target: &mut T, value: &Option<T>) {
+    if let Some(value) = value {
+        *target = value.clone();
+    }
+}
+
+// 兼容旧配置中 input = "file" 的单个字符串写法
+fn string_or_list<'de, D>(deserializer: D) -> std::result::Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrList {
+        String(String),
+        List(Vec<String>),
+    }
+
+    Ok(match StringOrList::deserialize(deserializer)? {
+        StringOrList::String(value) => vec![value],
+        StringOrList::List(values) => values,
+    })
+}
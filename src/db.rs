@@ -0,0 +1,235 @@
+use deadpool_postgres::{Config, ManagerConfig, Pool, RecyclingMethod, Runtime};
+use tokio_postgres::NoTls;
+
+use crate::{ProxyData, Result};
+
+// 在原有 proxies 表基础上追加的列，由 migrate_schema 幂等执行（scan 启动时与 db migrate）
+const SCHEMA_MIGRATIONS: &[&str] = &[
+    "ALTER TABLE proxies ADD COLUMN IF NOT EXISTS connect_ms INTEGER",
+    "ALTER TABLE proxies ADD COLUMN IF NOT EXISTS tls_handshake_ms INTEGER",
+    "ALTER TABLE proxies ADD COLUMN IF NOT EXISTS ttfb_ms INTEGER",
+    "ALTER TABLE proxies ADD COLUMN IF NOT EXISTS total_ms INTEGER",
+    "ALTER TABLE proxies ADD COLUMN IF NOT EXISTS cf_client_ip TEXT",
+    "ALTER TABLE proxies ADD COLUMN IF NOT EXISTS cf_colo TEXT",
+    "ALTER TABLE proxies ADD COLUMN IF NOT EXISTS cf_asn BIGINT",
+    "ALTER TABLE proxies ADD COLUMN IF NOT EXISTS cf_as_organization TEXT",
+    "ALTER TABLE proxies ADD COLUMN IF NOT EXISTS cf_country TEXT",
+    "ALTER TABLE proxies ADD COLUMN IF NOT EXISTS cf_city TEXT",
+    "ALTER TABLE proxies ADD COLUMN IF NOT EXISTS cf_http_protocol TEXT",
+    "ALTER TABLE proxies ADD COLUMN IF NOT EXISTS exit_differs BOOLEAN",
+    "ALTER TABLE proxies ADD COLUMN IF NOT EXISTS exit_country_code TEXT",
+    "ALTER TABLE proxies ADD COLUMN IF NOT EXISTS exit_country_name TEXT",
+    "ALTER TABLE proxies ADD COLUMN IF NOT EXISTS exit_city_code TEXT",
+    "ALTER TABLE proxies ADD COLUMN IF NOT EXISTS exit_city_name TEXT",
+    "ALTER TABLE proxies ADD COLUMN IF NOT EXISTS exit_asn_number TEXT",
+    "ALTER TABLE proxies ADD COLUMN IF NOT EXISTS exit_org_name TEXT",
+    "ALTER TABLE proxies ADD COLUMN IF NOT EXISTS entry_blocklist TEXT",
+    "ALTER TABLE proxies ADD COLUMN IF NOT EXISTS exit_blocklist TEXT",
+    "ALTER TABLE proxies ADD COLUMN IF NOT EXISTS attempts INTEGER",
+    "ALTER TABLE proxies ADD COLUMN IF NOT EXISTS source TEXT",
+    "ALTER TABLE proxies ADD COLUMN IF NOT EXISTS cf_region TEXT",
+    "ALTER TABLE proxies ADD COLUMN IF NOT EXISTS cf_tls_version TEXT",
+];
+
+// 初始化 PostgreSQL 连接池（必需）
+// 连接串来自配置（--database-url / CEKPROXY_DATABASE_URL / DATABASE_URL / cekproxy.toml）
+pub fn create_pg_pool(database_url: Option<&str>) -> Result<Pool> {
+    let database_url = match database_url {
+        Some(url) => {
+            if url.is_empty() {
+                return Err("DATABASE_URL is empty. PostgreSQL connection is required.".into());
+            }
+            eprintln!("✅ DATABASE_URL configured: {}...", &url.chars().take(20).collect::<String>());
+            url.to_string()
+        }
+        None => {
+            return Err("DATABASE_URL not set. PostgreSQL connection is required.".into());
+        }
+    };
+
+    let mut cfg = Config::new();
+    cfg.url = Some(database_url);
+    cfg.manager = Some(ManagerConfig {
+        recycling_method: RecyclingMethod::Fast,
+    });
+
+    match cfg.create_pool(Some(Runtime::Tokio1), NoTls) {
+        Ok(pool) => {
+            eprintln!("✅ PostgreSQL connection pool created successfully");
+            Ok(pool)
+        }
+        Err(e) => {
+            Err(format!("Failed to create PostgreSQL connection pool: {}", e).into())
+        }
+    }
+}
+
+// 测试数据库连接并验证表结构
+pub async fn test_database_connection(pool: &Pool) -> Result<()> {
+    eprintln!("🔍 Testing database connection...");
+
+    let client = pool.get().await.map_err(|e| {
+        eprintln!("❌ Failed to get database client: {}", e);
+        e
+    })?;
+
+    eprintln!("✅ Database connection successful");
+
+    // Check if proxies table exists
+    let table_check = client.query(
+        "SELECT EXISTS (
+            SELECT FROM information_schema.tables
+            WHERE table_name = 'proxies'
+        )",
+        &[]
+    ).await?;
+
+    if let Some(row) = table_check.first() {
+        let exists: bool = row.get(0);
+        if exists {
+            eprintln!("✅ Table 'proxies' exists");
+
+            // Get row count
+            let count_result = client.query("SELECT COUNT(*) FROM proxies", &[]).await?;
+            if let Some(row) = count_result.first() {
+                let count: i64 = row.get(0);
+                eprintln!("📊 Current proxy count in database: {}", count);
+            }
+        } else {
+            eprintln!("❌ Table 'proxies' does not exist! Please run schema.sql first.");
+            return Err("Table 'proxies' not found".into());
+        }
+    }
+
+    Ok(())
+}
+
+// 为 proxies 表补齐新增的列；只在会写入数据的路径上调用，db status 保持只读
+pub async fn migrate_schema(pool: &Pool) -> Result<()> {
+    let client = pool.get().await?;
+    for migration in SCHEMA_MIGRATIONS {
+        client.execute(*migration, &[]).await?;
+    }
+    eprintln!("✅ Schema up to date ({} column migrations checked)", SCHEMA_MIGRATIONS.len());
+    Ok(())
+}
+
+// 批量写入代理数据到 PostgreSQL
+pub async fn batch_insert_proxies(pool: &Pool, proxies: &[ProxyData], batch_time: chrono::DateTime<chrono::Utc>) -> Result<()> {
+    if proxies.is_empty() {
+        return Ok(());
+    }
+
+    let mut client = pool.get().await?;
+
+    // 开始事务
+    let transaction = client.transaction().await?;
+
+    // 批量插入（使用 UPSERT 策略）
+    let stmt = transaction.prepare(
+        "INSERT INTO proxies (ip, port, country_code, country_name, city_code, city_name, asn_number, org_name,
+                              connect_ms, tls_handshake_ms, ttfb_ms, total_ms,
+                              cf_client_ip, cf_colo, cf_asn, cf_as_organization, cf_country, cf_city, cf_region,
+                              cf_http_protocol, cf_tls_version,
+                              exit_differs, exit_country_code, exit_country_name, exit_city_code, exit_city_name,
+                              exit_asn_number, exit_org_name, entry_blocklist, exit_blocklist, attempts,
+                              source, updated_at)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19,
+                 $20, $21, $22, $23, $24, $25, $26, $27, $28, $29, $30, $31, $32, $33)
+         ON CONFLICT (ip, port)
+         DO UPDATE SET
+            country_code = EXCLUDED.country_code,
+            country_name = EXCLUDED.country_name,
+            city_code = EXCLUDED.city_code,
+            city_name = EXCLUDED.city_name,
+            asn_number = EXCLUDED.asn_number,
+            org_name = EXCLUDED.org_name,
+            connect_ms = EXCLUDED.connect_ms,
+            tls_handshake_ms = EXCLUDED.tls_handshake_ms,
+            ttfb_ms = EXCLUDED.ttfb_ms,
+            total_ms = EXCLUDED.total_ms,
+            cf_client_ip = EXCLUDED.cf_client_ip,
+            cf_colo = EXCLUDED.cf_colo,
+            cf_asn = EXCLUDED.cf_asn,
+            cf_as_organization = EXCLUDED.cf_as_organization,
+            cf_country = EXCLUDED.cf_country,
+            cf_city = EXCLUDED.cf_city,
+            cf_region = EXCLUDED.cf_region,
+            cf_http_protocol = EXCLUDED.cf_http_protocol,
+            cf_tls_version = EXCLUDED.cf_tls_version,
+            exit_differs = EXCLUDED.exit_differs,
+            exit_country_code = EXCLUDED.exit_country_code,
+            exit_country_name = EXCLUDED.exit_country_name,
+            exit_city_code = EXCLUDED.exit_city_code,
+            exit_city_name = EXCLUDED.exit_city_name,
+            exit_asn_number = EXCLUDED.exit_asn_number,
+            exit_org_name = EXCLUDED.exit_org_name,
+            entry_blocklist = EXCLUDED.entry_blocklist,
+            exit_blocklist = EXCLUDED.exit_blocklist,
+            attempts = EXCLUDED.attempts,
+            source = EXCLUDED.source,
+            updated_at = EXCLUDED.updated_at"
+    ).await?;
+
+    let mut inserted = 0;
+    for proxy in proxies {
+        transaction.execute(
+            &stmt,
+            &[
+                &proxy.ip,
+                &(proxy.port as i32),
+                &proxy.country_code,
+                &proxy.country_name,
+                &proxy.city_code,
+                &proxy.city_name,
+                &proxy.asn_number,
+                &proxy.org_name,
+                &(proxy.timings.connect_ms as i32),
+                &(proxy.timings.tls_handshake_ms as i32),
+                &(proxy.timings.ttfb_ms as i32),
+                &(proxy.timings.total_ms as i32),
+                &proxy.cf.client_ip,
+                &proxy.cf.colo,
+                &(proxy.cf.asn as i64),
+                &proxy.cf.as_organization,
+                &proxy.cf.country,
+                &proxy.cf.city,
+                &proxy.cf.region,
+                &proxy.cf.http_protocol,
+                &proxy.cf.tls_version,
+                &proxy.exit_differs,
+                &proxy.exit_geo.country_code,
+                &proxy.exit_geo.country_name,
+                &proxy.exit_geo.city_code,
+                &proxy.exit_geo.city_name,
+                &proxy.exit_geo.asn_number,
+                &proxy.exit_geo.org_name,
+                &proxy.entry_blocklist,
+                &proxy.exit_blocklist,
+                &(proxy.attempts as i32),
+                &proxy.source,
+                &batch_time,
+            ],
+        ).await?;
+        inserted += 1;
+    }
+
+    // 提交事务
+    transaction.commit().await?;
+
+    eprintln!("✅ Inserted/Updated {} proxies to PostgreSQL", inserted);
+    Ok(())
+}
+
+// 清理旧数据（保留本次更新的数据）
+pub async fn cleanup_old_proxies(pool: &Pool, batch_time: chrono::DateTime<chrono::Utc>) -> Result<()> {
+    let client = pool.get().await?;
+
+    let rows_deleted = client.execute(
+        "DELETE FROM proxies WHERE updated_at < $1",
+        &[&batch_time],
+    ).await?;
+
+    eprintln!("✅ Cleaned up {} old proxy records from PostgreSQL", rows_deleted);
+    Ok(())
+}
@@ -0,0 +1,203 @@
+use std::fmt;
+use std::io;
+
+// 代理检测失败的原因分类
+// kind() 返回稳定的 snake_case 标签，用于计数与 rejected 输出
+#[derive(Debug, Clone)]
+pub enum CheckError {
+    InvalidInput(String),
+    ConnectRefused,
+    ConnectTimeout,
+    ConnectFailed(String),
+    // 本机资源耗尽（文件描述符、临时端口、缓冲区），说明并发过高而不是代理问题
+    LocalResource(String),
+    TlsHandshakeTimeout,
+    TlsHandshakeFailed(String),
+    CertificateMismatch(String),
+    // 发出请求后在 first_byte 时限内没有收到任何响应
+    FirstByteTimeout,
+    ReadTimeout,
+    ConnectionReset,
+    Io(String),
+    InvalidHttpResponse(String),
+    // 响应头或响应体超过上限（字节）
+    ResponseTooLarge(usize),
+    HttpStatus(u16),
+    InvalidJson(String),
+    MissingClientIp,
+    SameAsOrigin,
+    // 通过的 resolver 数量不足 quorum
+    QuorumNotMet { passed: usize, required: usize },
+    // 足够多的 resolver 通过，但看到的出口 IP 不一致，同一出口 IP 的数量不足 quorum
+    ExitIpDisagreement { agreed: usize, passed: usize, required: usize },
+    Filtered { side: FilterSide, kind: FilterKind },
+}
+
+// 被过滤的是入口 IP 还是出口 IP
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterSide {
+    Entry,
+    Exit,
+}
+
+// 黑名单过滤器类型
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterKind {
+    InvalidIp,
+    // 匿名IP库命中的类别，如 VPN+公共代理
+    Anonymous(String),
+    AbuseIpDb,
+    FireHol,
+}
+
+impl CheckError {
+    // kind() 可能返回的全部标签，用于校验配置中的错误类别
+    pub const KINDS: &'static [&'static str] = &[
+        "invalid_input",
+        "connect_refused",
+        "connect_timeout",
+        "connect_failed",
+        "local_resource",
+        "tls_handshake_timeout",
+        "tls_handshake_failed",
+        "certificate_mismatch",
+        "first_byte_timeout",
+        "read_timeout",
+        "connection_reset",
+        "io_error",
+        "invalid_http_response",
+        "response_too_large",
+        "http_status",
+        "invalid_json",
+        "missing_client_ip",
+        "same_as_origin",
+        "quorum_not_met",
+        "exit_ip_disagreement",
+        "filtered_invalid_ip",
+        "filtered_anonymous",
+        "filtered_abuseipdb",
+        "filtered_firehol",
+    ];
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CheckError::InvalidInput(_) => "invalid_input",
+            CheckError::ConnectRefused => "connect_refused",
+            CheckError::ConnectTimeout => "connect_timeout",
+            CheckError::ConnectFailed(_) => "connect_failed",
+            CheckError::LocalResource(_) => "local_resource",
+            CheckError::TlsHandshakeTimeout => "tls_handshake_timeout",
+            CheckError::TlsHandshakeFailed(_) => "tls_handshake_failed",
+            CheckError::CertificateMismatch(_) => "certificate_mismatch",
+            CheckError::FirstByteTimeout => "first_byte_timeout",
+            CheckError::ReadTimeout => "read_timeout",
+            CheckError::ConnectionReset => "connection_reset",
+            CheckError::Io(_) => "io_error",
+            CheckError::InvalidHttpResponse(_) => "invalid_http_response",
+            CheckError::ResponseTooLarge(_) => "response_too_large",
+            CheckError::HttpStatus(_) => "http_status",
+            CheckError::InvalidJson(_) => "invalid_json",
+            CheckError::MissingClientIp => "missing_client_ip",
+            CheckError::SameAsOrigin => "same_as_origin",
+            CheckError::QuorumNotMet { .. } => "quorum_not_met",
+            CheckError::ExitIpDisagreement { .. } => "exit_ip_disagreement",
+            CheckError::Filtered { kind, .. } => match kind {
+                FilterKind::InvalidIp => "filtered_invalid_ip",
+                FilterKind::Anonymous(_) => "filtered_anonymous",
+                FilterKind::AbuseIpDb => "filtered_abuseipdb",
+                FilterKind::FireHol => "filtered_firehol",
+            },
+        }
+    }
+
+    // 按 I/O 错误类型归类 TCP 连接失败
+    pub fn from_connect_error(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::ConnectionRefused => CheckError::ConnectRefused,
+            io::ErrorKind::TimedOut => CheckError::ConnectTimeout,
+            io::ErrorKind::AddrNotAvailable | io::ErrorKind::OutOfMemory => CheckError::LocalResource(e.to_string()),
+            _ => {
+                // EMFILE/ENFILE/ENOBUFS 没有对应的稳定 ErrorKind，只能按错误信息判断
+                let message = e.to_string();
+                if message.contains("Too many open files") || message.contains("No buffer space") {
+                    CheckError::LocalResource(message)
+                } else {
+                    CheckError::ConnectFailed(message)
+                }
+            }
+        }
+    }
+
+    // 证书校验失败与其他握手错误分开统计
+    pub fn from_tls_error(message: String) -> Self {
+        let lower = message.to_ascii_lowercase();
+        if lower.contains("certificate") || lower.contains("hostname") {
+            CheckError::CertificateMismatch(message)
+        } else {
+            CheckError::TlsHandshakeFailed(message)
+        }
+    }
+
+    // 读写阶段的 I/O 错误
+    pub fn from_io_error(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::BrokenPipe
+            | io::ErrorKind::UnexpectedEof => CheckError::ConnectionReset,
+            io::ErrorKind::TimedOut => CheckError::ReadTimeout,
+            _ => CheckError::Io(e.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for FilterKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FilterKind::InvalidIp => write!(f, "Invalid IP format"),
+            FilterKind::Anonymous(reason) => write!(f, "匿名IP: {}", reason),
+            FilterKind::AbuseIpDb => write!(f, "AbuseIPDB 黑名单"),
+            FilterKind::FireHol => write!(f, "FireHOL CIDR 黑名单"),
+        }
+    }
+}
+
+impl fmt::Display for CheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckError::InvalidInput(detail) => write!(f, "invalid input: {}", detail),
+            CheckError::ConnectRefused => write!(f, "connection refused"),
+            CheckError::ConnectTimeout => write!(f, "connect timed out"),
+            CheckError::ConnectFailed(detail) => write!(f, "connect failed: {}", detail),
+            CheckError::LocalResource(detail) => write!(f, "local resource exhausted: {}", detail),
+            CheckError::TlsHandshakeTimeout => write!(f, "TLS handshake timed out"),
+            CheckError::TlsHandshakeFailed(detail) => write!(f, "TLS handshake failed: {}", detail),
+            CheckError::CertificateMismatch(detail) => write!(f, "certificate mismatch: {}", detail),
+            CheckError::FirstByteTimeout => write!(f, "no response before first-byte timeout"),
+            CheckError::ReadTimeout => write!(f, "response timed out"),
+            CheckError::ConnectionReset => write!(f, "connection reset"),
+            CheckError::Io(detail) => write!(f, "I/O error: {}", detail),
+            CheckError::InvalidHttpResponse(detail) => write!(f, "invalid HTTP response: {}", detail),
+            CheckError::ResponseTooLarge(limit) => write!(f, "response exceeds {} bytes", limit),
+            CheckError::HttpStatus(status) => write!(f, "HTTP status {}", status),
+            CheckError::InvalidJson(detail) => write!(f, "invalid JSON: {}", detail),
+            CheckError::MissingClientIp => write!(f, "no clientIp in response"),
+            CheckError::SameAsOrigin => write!(f, "same IP as original"),
+            CheckError::QuorumNotMet { passed, required } => {
+                write!(f, "only {} of {} required resolvers passed", passed, required)
+            }
+            CheckError::ExitIpDisagreement { agreed, passed, required } => {
+                write!(f, "resolvers saw different exit IPs: at most {} of {} agree, {} required", agreed, passed, required)
+            }
+            CheckError::Filtered { side, kind } => {
+                let side = match side {
+                    FilterSide::Entry => "entry",
+                    FilterSide::Exit => "exit",
+                };
+                write!(f, "{} IP filtered ({})", side, kind)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CheckError {}
@@ -0,0 +1,214 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::net::IpAddr;
+
+use ipnetwork::IpNetwork;
+
+use crate::config::{FilterConfig, FilterTarget, ScanConfig};
+use crate::Result;
+
+// 保留/不可路由的地址段（RFC 6890 等），不可能是公网代理
+const RESERVED_RANGES: &[&str] = &[
+    "0.0.0.0/8",
+    "10.0.0.0/8",
+    "100.64.0.0/10",
+    "127.0.0.0/8",
+    "169.254.0.0/16",
+    "172.16.0.0/12",
+    "192.0.0.0/24",
+    "192.0.2.0/24",
+    "192.88.99.0/24",
+    "192.168.0.0/16",
+    "198.18.0.0/15",
+    "198.51.100.0/24",
+    "203.0.113.0/24",
+    "224.0.0.0/4",
+    "240.0.0.0/4",
+    "::/128",
+    "::1/128",
+    "::ffff:0:0/96",
+    "100::/64",
+    "2001:db8::/32",
+    "fc00::/7",
+    "fe80::/10",
+    "ff00::/8",
+];
+
+// 扫描前排除的地址，在读取输入时检查，被排除的地址不会产生任何连接
+// reserved 与 user 作用于所有输入；FireHOL 只作用于从 CIDR 网段枚举出的地址，
+// 明确列出的代理仍按 filters 在检测后过滤并记录原因
+#[derive(Default)]
+pub struct Exclusions {
+    reserved: RangeSet,
+    user: RangeSet,
+    firehol: RangeSet,
+}
+
+impl Exclusions {
+    pub fn load(scan: &ScanConfig, filters: &FilterConfig, firehol_cidrs: &[IpNetwork]) -> Result<Self> {
+        let mut exclusions = Exclusions::default();
+
+        if scan.exclude_reserved {
+            for range in RESERVED_RANGES {
+                exclusions.reserved.insert(range.parse().expect("reserved ranges are valid CIDRs"));
+            }
+        }
+
+        for path in &scan.exclude {
+            let file = File::open(path).map_err(|e| format!("Could not read exclusion list {}: {}", path, e))?;
+            let mut count = 0;
+            for (index, line) in BufReader::new(file).lines().enumerate() {
+                let line = line?;
+                // 支持行尾注释
+                let entry = line.split('#').next().unwrap_or_default().trim();
+                if entry.is_empty() {
+                    continue;
+                }
+                let network = entry
+                    .parse::<IpNetwork>()
+                    .map_err(|_| format!("{}:{}: invalid IP or CIDR '{}'", path, index + 1, entry))?;
+                exclusions.user.insert(network);
+                count += 1;
+            }
+            eprintln!("Loaded {} exclusions from {}", count, path);
+        }
+
+        // filters.target = exit 时入口 IP 命中 FireHOL 仍需检测
+        if filters.firehol && filters.target != FilterTarget::Exit {
+            for network in firehol_cidrs {
+                exclusions.firehol.insert(*network);
+            }
+        }
+
+        exclusions.reserved.build();
+        exclusions.user.build();
+        exclusions.firehol.build();
+        Ok(exclusions)
+    }
+
+    // 命中时返回排除原因；from_range 表示该地址是从 CIDR 网段枚举出来的
+    pub fn reason(&self, ip: IpAddr, from_range: bool) -> Option<&'static str> {
+        if self.reserved.contains(ip) {
+            Some("reserved")
+        } else if self.user.contains(ip) {
+            Some("user")
+        } else if from_range && self.firehol.contains(ip) {
+            Some("firehol")
+        } else {
+            None
+        }
+    }
+}
+
+// 按起始地址排序并合并重叠或相邻的地址区间，二分查找
+#[derive(Default)]
+struct RangeSet {
+    v4: Vec<(u128, u128)>,
+    v6: Vec<(u128, u128)>,
+}
+
+impl RangeSet {
+    fn insert(&mut self, network: IpNetwork) {
+        let range = (ip_to_u128(network.network()), ip_to_u128(network.broadcast()));
+        match network {
+            IpNetwork::V4(_) => self.v4.push(range),
+            IpNetwork::V6(_) => self.v6.push(range),
+        }
+    }
+
+    fn build(&mut self) {
+        merge(&mut self.v4);
+        merge(&mut self.v6);
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        let ranges = if ip.is_ipv4() { &self.v4 } else { &self.v6 };
+        let value = ip_to_u128(ip);
+        let index = ranges.partition_point(|&(start, _)| start <= value);
+        index > 0 && ranges[index - 1].1 >= value
+    }
+}
+
+fn merge(ranges: &mut Vec<(u128, u128)>) {
+    ranges.sort_unstable();
+    let mut merged: Vec<(u128, u128)> = Vec::with_capacity(ranges.len());
+    for &(start, end) in ranges.iter() {
+        match merged.last_mut() {
+            Some(last) if start <= last.1.saturating_add(1) => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    *ranges = merged;
+}
+
+pub(crate) fn ip_to_u128(ip: IpAddr) -> u128 {
+    match ip {
+        IpAddr::V4(v4) => u32::from(v4) as u128,
+        IpAddr::V6(v6) => u128::from(v6),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range_set(networks: &[&str]) -> RangeSet {
+        let mut set = RangeSet::default();
+        for network in networks {
+            set.insert(network.parse().unwrap());
+        }
+        set.build();
+        set
+    }
+
+    fn contains(set: &RangeSet, ip: &str) -> bool {
+        set.contains(ip.parse().unwrap())
+    }
+
+    #[test]
+    fn merges_overlapping_and_adjacent_ranges() {
+        let set = range_set(&["10.0.0.0/24", "10.0.0.128/25", "10.0.1.0/24", "10.0.3.0/24", "9.0.0.0/8"]);
+        assert_eq!(
+            set.v4,
+            [
+                (u32::from_be_bytes([9, 0, 0, 0]) as u128, u32::from_be_bytes([10, 0, 1, 255]) as u128),
+                (u32::from_be_bytes([10, 0, 3, 0]) as u128, u32::from_be_bytes([10, 0, 3, 255]) as u128),
+            ]
+        );
+    }
+
+    #[test]
+    fn contains_checks_range_boundaries() {
+        let set = range_set(&["10.0.0.0/24", "10.0.2.0/24", "1.2.3.4", "2001:db8::/32"]);
+        assert!(contains(&set, "10.0.0.0"));
+        assert!(contains(&set, "10.0.0.255"));
+        assert!(!contains(&set, "10.0.1.0"));
+        assert!(!contains(&set, "9.255.255.255"));
+        assert!(contains(&set, "10.0.2.128"));
+        assert!(!contains(&set, "10.0.3.0"));
+        assert!(contains(&set, "1.2.3.4"));
+        assert!(!contains(&set, "1.2.3.5"));
+        assert!(contains(&set, "2001:db8:ffff::1"));
+        assert!(!contains(&set, "2001:db9::"));
+        // IPv4 与 IPv6 分开存放，数值相同也不会误判
+        assert!(!contains(&set, "::a00:1"));
+    }
+
+    #[test]
+    fn contains_handles_host_bits_and_empty_sets() {
+        let set = range_set(&["10.0.0.77/24"]);
+        assert!(contains(&set, "10.0.0.1"));
+        assert!(!contains(&RangeSet::default(), "10.0.0.1"));
+    }
+
+    #[test]
+    fn reserved_ranges_are_valid() {
+        let set = range_set(RESERVED_RANGES);
+        for ip in ["10.1.2.3", "127.0.0.1", "192.168.1.1", "100.64.0.1", "224.0.0.1", "255.255.255.255", "::1", "fe80::1", "fd00::1"] {
+            assert!(contains(&set, ip), "{}", ip);
+        }
+        for ip in ["1.1.1.1", "8.8.8.8", "104.16.0.1", "2606:4700::1"] {
+            assert!(!contains(&set, ip), "{}", ip);
+        }
+    }
+}
@@ -0,0 +1,357 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use ipnetwork::IpNetwork;
+use maxminddb::{geoip2, Reader};
+use serde::Serialize;
+
+use crate::config::{DataConfig, FilterConfig, FilterTarget};
+use crate::error::{CheckError, FilterKind, FilterSide};
+use crate::Result;
+
+// 单个 IP 的地理位置与 ASN 信息
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GeoInfo {
+    pub country_code: String,
+    pub country_name: String,
+    pub city_code: String,
+    pub city_name: String,
+    pub asn_number: String,
+    pub org_name: String,
+}
+
+// GeoIP 数据库与黑名单（扫描期间只读，跨任务共享）
+pub struct GeoDatabases {
+    pub country_reader: Arc<Reader<Vec<u8>>>,
+    pub city_reader: Option<Arc<Reader<Vec<u8>>>>,
+    pub asn_reader: Option<Arc<Reader<Vec<u8>>>>,
+    pub anonymous_reader: Option<Arc<Reader<Vec<u8>>>>,
+    pub abuse_ips: HashSet<IpAddr>,
+    pub firehol_cidrs: Vec<IpNetwork>,
+    pub filter_target: FilterTarget,
+}
+
+impl GeoDatabases {
+    // 按配置加载所有数据库；只有 Country 数据库是必需的，被关闭的过滤器不加载数据
+    pub fn load(args: &DataConfig, filters: &FilterConfig) -> Result<Self> {
+        let country_reader = Arc::new(Reader::open_readfile(&args.country_db)?);
+        eprintln!("Loaded Country database: {}", args.country_db);
+
+        let city_reader = match Reader::open_readfile(&args.city_db) {
+            Ok(reader) => {
+                eprintln!("Loaded City database: {}", args.city_db);
+                Some(Arc::new(reader))
+            }
+            Err(e) => {
+                eprintln!("Warning: Could not load City database ({}): {}. City info will show as '未知'.", args.city_db, e);
+                None
+            }
+        };
+
+        // Initialize ASN database reader (optional)
+        let asn_reader = match Reader::open_readfile(&args.asn_db) {
+            Ok(reader) => {
+                eprintln!("Loaded ASN database: {}", args.asn_db);
+                Some(Arc::new(reader))
+            }
+            Err(e) => {
+                eprintln!("Warning: Could not load ASN database ({}): {}. ASN info will show as empty.", args.asn_db, e);
+                None
+            }
+        };
+
+        // Initialize Anonymous IP database reader (optional)
+        let anonymous_reader = if !filters.anonymous {
+            eprintln!("Anonymous IP filtering disabled by config");
+            None
+        } else {
+            match Reader::open_readfile(&args.anonymous_db) {
+                Ok(reader) => {
+                    eprintln!("Loaded Anonymous IP database: {}", args.anonymous_db);
+                    Some(Arc::new(reader))
+                }
+                Err(e) => {
+                    eprintln!("Warning: Could not load Anonymous IP database ({}): {}. Anonymous IP filtering will be disabled.", args.anonymous_db, e);
+                    None
+                }
+            }
+        };
+
+        let abuse_ips = if filters.abuse_ips {
+            load_abuse_ips(&args.abuse_ips)
+        } else {
+            eprintln!("AbuseIPDB filtering disabled by config");
+            HashSet::new()
+        };
+
+        let firehol_cidrs = if filters.firehol {
+            load_firehol_cidrs(&args.firehol_cidrs)
+        } else {
+            eprintln!("FireHOL CIDR filtering disabled by config");
+            Vec::new()
+        };
+
+        Ok(GeoDatabases {
+            country_reader,
+            city_reader,
+            asn_reader,
+            anonymous_reader,
+            abuse_ips,
+            firehol_cidrs,
+            filter_target: filters.target,
+        })
+    }
+
+    // 依次检查匿名IP库、AbuseIPDB、FireHOL，命中时返回过滤原因
+    pub fn filter_reason(&self, ip: &str) -> Option<FilterKind> {
+        let ip_addr = match ip.parse::<IpAddr>() {
+            Ok(addr) => addr,
+            Err(_) => return Some(FilterKind::InvalidIp),
+        };
+
+        // 检查是否为匿名IP（VPN/公共代理/Tor）- 仅当数据库可用时
+        if let Some(anon_reader) = self.anonymous_reader.as_deref() {
+            let (is_anonymous, reason) = is_anonymous_ip(anon_reader, ip);
+            if is_anonymous {
+                return Some(FilterKind::Anonymous(reason));
+            }
+        }
+
+        // 检查是否在 AbuseIPDB 黑名单中
+        if !self.abuse_ips.is_empty() && self.abuse_ips.contains(&ip_addr) {
+            return Some(FilterKind::AbuseIpDb);
+        }
+
+        // 检查是否在 FireHOL CIDR 黑名单中
+        if !self.firehol_cidrs.is_empty() && is_ip_in_cidr_list(ip_addr, &self.firehol_cidrs) {
+            return Some(FilterKind::FireHol);
+        }
+
+        None
+    }
+
+    // 按配置的过滤目标决定是否丢弃代理，参数为入口/出口 IP 各自的过滤原因
+    pub fn rejection(&self, entry_reason: Option<&FilterKind>, exit_reason: Option<&FilterKind>) -> Option<CheckError> {
+        let entry = entry_reason.map(|kind| CheckError::Filtered { side: FilterSide::Entry, kind: kind.clone() });
+        let exit = exit_reason.map(|kind| CheckError::Filtered { side: FilterSide::Exit, kind: kind.clone() });
+        match self.filter_target {
+            FilterTarget::Entry => entry,
+            FilterTarget::Exit => exit,
+            FilterTarget::Both => entry.or(exit),
+        }
+    }
+
+    // 查询国家/城市/ASN 信息，数据库缺失时对应字段为空
+    pub fn lookup(&self, ip: &str) -> GeoInfo {
+        let (country_code, country_name, city_code, city_name) =
+            get_geo_info(&self.country_reader, self.city_reader.as_deref(), ip);
+
+        let (asn_number, org_name) = if let Some(reader) = self.asn_reader.as_deref() {
+            get_asn_info(reader, ip)
+        } else {
+            (String::new(), String::new())
+        };
+
+        GeoInfo {
+            country_code,
+            country_name,
+            city_code,
+            city_name,
+            asn_number,
+            org_name,
+        }
+    }
+}
+
+// 读取 AbuseIPDB 黑名单 IP 列表
+pub fn load_abuse_ips(file_path: &str) -> HashSet<IpAddr> {
+    let mut abuse_ips = HashSet::new();
+
+    match File::open(file_path) {
+        Ok(file) => {
+            let reader = BufReader::new(file);
+            for line in reader.lines().map_while(|line| line.ok()) {
+                // 格式: ip,country_code,abuse_confidence_score
+                let parts: Vec<&str> = line.split(',').collect();
+                if !parts.is_empty() {
+                    if let Ok(ip) = parts[0].trim().parse::<IpAddr>() {
+                        abuse_ips.insert(ip);
+                    }
+                }
+            }
+            eprintln!("Loaded {} abuse IPs from {}", abuse_ips.len(), file_path);
+        }
+        Err(e) => {
+            eprintln!("Warning: Could not load abuse IP list ({}): {}. Abuse IP filtering will be disabled.", file_path, e);
+        }
+    }
+
+    abuse_ips
+}
+
+// 读取 FireHOL CIDR 网段列表
+pub fn load_firehol_cidrs(file_path: &str) -> Vec<IpNetwork> {
+    let mut cidrs = Vec::new();
+
+    match File::open(file_path) {
+        Ok(file) => {
+            let reader = BufReader::new(file);
+            for line in reader.lines().map_while(|line| line.ok()) {
+                let line = line.trim();
+                if !line.is_empty() {
+                    if let Ok(network) = line.parse::<IpNetwork>() {
+                        cidrs.push(network);
+                    }
+                }
+            }
+            eprintln!("Loaded {} CIDR ranges from {}", cidrs.len(), file_path);
+        }
+        Err(e) => {
+            eprintln!("Warning: Could not load FireHOL CIDR list ({}): {}. CIDR filtering will be disabled.", file_path, e);
+        }
+    }
+
+    cidrs
+}
+
+// 检查 IP 是否在 CIDR 网段内
+pub fn is_ip_in_cidr_list(ip: IpAddr, cidrs: &[IpNetwork]) -> bool {
+    cidrs.iter().any(|network| network.contains(ip))
+}
+
+// 查询 IP 地理位置信息
+// 返回: (国家代码, 国家名, 城市代码, 城市名)
+pub fn get_geo_info(
+    country_reader: &Reader<Vec<u8>>,
+    city_reader: Option<&Reader<Vec<u8>>>,
+    ip_str: &str,
+) -> (String, String, String, String) {
+    let ip: IpAddr = match ip_str.parse() {
+        Ok(ip) => ip,
+        Err(_) => return (String::new(), String::new(), String::new(), String::new()),
+    };
+
+    // 查询国家信息
+    let (country_code, country_name) = match country_reader.lookup::<geoip2::Country>(ip) {
+        Ok(country_data) => {
+            let code = country_data
+                .country
+                .as_ref()
+                .and_then(|c| c.iso_code)
+                .unwrap_or("")
+                .to_string();
+
+            let name = country_data
+                .country
+                .as_ref()
+                .and_then(|c| c.names.as_ref())
+                .and_then(|names| {
+                    names.get("zh-CN")
+                        .or_else(|| names.get("en"))
+                        .map(|s| s.to_string())
+                })
+                .unwrap_or_default();
+
+            (code, name)
+        }
+        Err(_) => (String::new(), String::new()),
+    };
+
+    // 查询城市信息（如果有城市数据库）
+    let (city_code, city_name) = if let Some(reader) = city_reader {
+        match reader.lookup::<geoip2::City>(ip) {
+            Ok(city_data) => {
+                // GeoLite2 没有城市代码，使用城市名的英文作为代码
+                let code = city_data
+                    .city
+                    .as_ref()
+                    .and_then(|c| c.names.as_ref())
+                    .and_then(|names| names.get("en").map(|s| s.to_string()))
+                    .unwrap_or_default();
+
+                let name = city_data
+                    .city
+                    .as_ref()
+                    .and_then(|c| c.names.as_ref())
+                    .and_then(|names| {
+                        names.get("zh-CN")
+                            .or_else(|| names.get("en"))
+                            .map(|s| s.to_string())
+                    })
+                    .unwrap_or_default();
+
+                (code, name)
+            }
+            Err(_) => (String::new(), String::new()),
+        }
+    } else {
+        (String::new(), String::new())
+    };
+
+    (country_code, country_name, city_code, city_name)
+}
+
+// 查询 ASN 信息
+// 返回: (ASN 编号, 组织名)
+pub fn get_asn_info(
+    asn_reader: &Reader<Vec<u8>>,
+    ip_str: &str,
+) -> (String, String) {
+    let ip: IpAddr = match ip_str.parse() {
+        Ok(ip) => ip,
+        Err(_) => return (String::new(), String::new()),
+    };
+
+    match asn_reader.lookup::<geoip2::Asn>(ip) {
+        Ok(asn_data) => {
+            let asn_number = asn_data
+                .autonomous_system_number
+                .map(|n| n.to_string())
+                .unwrap_or_default();
+
+            let org_name = asn_data
+                .autonomous_system_organization
+                .unwrap_or("")
+                .to_string();
+
+            (asn_number, org_name)
+        }
+        Err(_) => (String::new(), String::new()),
+    }
+}
+
+// 检查 IP 是否为匿名代理（VPN/公共代理/Tor）
+pub fn is_anonymous_ip(
+    anonymous_reader: &Reader<Vec<u8>>,
+    ip_str: &str,
+) -> (bool, String) {
+    let ip: IpAddr = match ip_str.parse() {
+        Ok(ip) => ip,
+        Err(_) => return (false, "无法解析IP".to_string()),
+    };
+
+    match anonymous_reader.lookup::<geoip2::AnonymousIp>(ip) {
+        Ok(anonymous_data) => {
+            let is_vpn = anonymous_data.is_anonymous_vpn.unwrap_or(false);
+            let is_proxy = anonymous_data.is_public_proxy.unwrap_or(false);
+            let is_tor = anonymous_data.is_tor_exit_node.unwrap_or(false);
+
+            if is_vpn || is_proxy || is_tor {
+                let mut reasons = Vec::new();
+                if is_vpn { reasons.push("VPN"); }
+                if is_proxy { reasons.push("公共代理"); }
+                if is_tor { reasons.push("Tor出口节点"); }
+                (true, reasons.join("+"))
+            } else {
+                (false, "正常IP".to_string())
+            }
+        }
+        Err(_) => {
+            // 数据库中没有记录，视为正常IP（不在匿名IP列表中）
+            (false, "未知(默认允许)".to_string())
+        }
+    }
+}
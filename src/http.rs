@@ -0,0 +1,281 @@
+use std::io;
+
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt};
+
+use crate::error::CheckError;
+
+// 状态行加全部响应头的上限
+const MAX_HEAD_BYTES: usize = 16 * 1024;
+
+// HTTP/1.1 响应头部分
+pub struct ResponseHead {
+    pub status: u16,
+    // 头名统一为小写
+    pub headers: Vec<(String, String)>,
+}
+
+impl ResponseHead {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn is_chunked(&self) -> bool {
+        // 只有最后一个编码是 chunked 时才按分块解析
+        self.header("transfer-encoding")
+            .and_then(|value| value.rsplit(',').next())
+            .is_some_and(|last| last.trim().eq_ignore_ascii_case("chunked"))
+    }
+
+    fn content_length(&self) -> Result<Option<usize>, CheckError> {
+        match self.header("content-length") {
+            Some(value) => value
+                .trim()
+                .parse()
+                .map(Some)
+                .map_err(|_| CheckError::InvalidHttpResponse(format!("Invalid Content-Length '{}'", value))),
+            None => Ok(None),
+        }
+    }
+}
+
+// 读取状态行与响应头，跳过 1xx 中间响应
+// 所有 1xx 响应与最终响应共用 MAX_HEAD_BYTES，无限的 100 Continue 也会被截止
+pub async fn read_head<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<ResponseHead, CheckError> {
+    let mut head_bytes = 0;
+    loop {
+        let status_line = read_line(reader, &mut head_bytes).await?;
+        let status = parse_status_line(&status_line)?;
+
+        let mut headers = Vec::new();
+        loop {
+            let line = read_line(reader, &mut head_bytes).await?;
+            if line.is_empty() {
+                break;
+            }
+            let (name, value) = line
+                .split_once(':')
+                .ok_or_else(|| CheckError::InvalidHttpResponse(format!("Malformed header line '{}'", line)))?;
+            headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+        }
+
+        if !(100..200).contains(&status) {
+            return Ok(ResponseHead { status, headers });
+        }
+    }
+}
+
+// 按 Transfer-Encoding / Content-Length 读取响应体，超过 max_bytes 直接报错
+pub async fn read_body<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    head: &ResponseHead,
+    max_bytes: usize,
+) -> Result<Vec<u8>, CheckError> {
+    if head.is_chunked() {
+        return read_chunked(reader, max_bytes).await;
+    }
+
+    if let Some(length) = head.content_length()? {
+        if length > max_bytes {
+            return Err(CheckError::ResponseTooLarge(max_bytes));
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).await.map_err(truncated)?;
+        return Ok(body);
+    }
+
+    // 没有长度信息时读到连接关闭为止，多读一个字节用于判断是否超限
+    let mut body = Vec::new();
+    let result = reader.take(max_bytes as u64 + 1).read_to_end(&mut body).await;
+    match result {
+        Ok(_) => {}
+        // rustls melaporkan UnexpectedEof jika server menutup koneksi tanpa close_notify
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {}
+        Err(e) => return Err(CheckError::from_io_error(e)),
+    }
+    if body.len() > max_bytes {
+        return Err(CheckError::ResponseTooLarge(max_bytes));
+    }
+    Ok(body)
+}
+
+async fn read_chunked<R: AsyncBufRead + Unpin>(reader: &mut R, max_bytes: usize) -> Result<Vec<u8>, CheckError> {
+    let mut body = Vec::new();
+    // 分块大小行与 trailer 同样计入头部上限，防止无限长的行
+    let mut line_bytes = 0;
+
+    loop {
+        let size_line = read_line(reader, &mut line_bytes).await?;
+        // 忽略 chunk extension，例如 "1a;name=value"
+        let size_hex = size_line.split(';').next().unwrap_or("").trim();
+        let size = usize::from_str_radix(size_hex, 16)
+            .map_err(|_| CheckError::InvalidHttpResponse(format!("Invalid chunk size '{}'", size_line)))?;
+
+        if size == 0 {
+            // 读取并丢弃 trailer，直到空行
+            while !read_line(reader, &mut line_bytes).await?.is_empty() {}
+            return Ok(body);
+        }
+
+        // size 来自远端，可能接近 usize::MAX，不能直接与 body.len() 相加
+        if size > max_bytes - body.len() {
+            return Err(CheckError::ResponseTooLarge(max_bytes));
+        }
+
+        let start = body.len();
+        body.resize(start + size, 0);
+        reader.read_exact(&mut body[start..]).await.map_err(truncated)?;
+
+        if !read_line(reader, &mut line_bytes).await?.is_empty() {
+            return Err(CheckError::InvalidHttpResponse("Missing CRLF after chunk".to_string()));
+        }
+    }
+}
+
+// 读取一行（去掉 CRLF），累计字节数超过 MAX_HEAD_BYTES 时报错
+async fn read_line<R: AsyncBufRead + Unpin>(reader: &mut R, total: &mut usize) -> Result<String, CheckError> {
+    let mut line = Vec::new();
+    let remaining = MAX_HEAD_BYTES.saturating_sub(*total) as u64;
+    let n = (&mut *reader)
+        .take(remaining + 1)
+        .read_until(b'\n', &mut line)
+        .await
+        .map_err(truncated)?;
+
+    *total += n;
+    if *total > MAX_HEAD_BYTES {
+        return Err(CheckError::ResponseTooLarge(MAX_HEAD_BYTES));
+    }
+    if !line.ends_with(b"\n") {
+        return Err(CheckError::InvalidHttpResponse("Unexpected end of response".to_string()));
+    }
+
+    line.pop();
+    if line.ends_with(b"\r") {
+        line.pop();
+    }
+    String::from_utf8(line).map_err(|_| CheckError::InvalidHttpResponse("Non UTF-8 header line".to_string()))
+}
+
+// 状态行: HTTP/1.1 200 OK
+fn parse_status_line(line: &str) -> Result<u16, CheckError> {
+    let mut parts = line.splitn(3, ' ');
+    let version = parts.next().unwrap_or("");
+    if !version.starts_with("HTTP/1.") {
+        return Err(CheckError::InvalidHttpResponse(format!("Malformed status line '{}'", line)));
+    }
+    parts
+        .next()
+        .and_then(|code| code.parse::<u16>().ok())
+        .filter(|code| (100..1000).contains(code))
+        .ok_or_else(|| CheckError::InvalidHttpResponse(format!("Malformed status line '{}'", line)))
+}
+
+// 响应体读取过程中连接提前关闭
+fn truncated(e: io::Error) -> CheckError {
+    if e.kind() == io::ErrorKind::UnexpectedEof {
+        CheckError::InvalidHttpResponse("Unexpected end of response".to_string())
+    } else {
+        CheckError::from_io_error(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn parse(response: &[u8], max_bytes: usize) -> Result<(u16, Vec<u8>), CheckError> {
+        let mut reader = response;
+        let head = read_head(&mut reader).await?;
+        let body = read_body(&mut reader, &head, max_bytes).await?;
+        Ok((head.status, body))
+    }
+
+    #[tokio::test]
+    async fn reads_content_length_body() {
+        let (status, body) = parse(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello", 1024).await.unwrap();
+        assert_eq!(status, 200);
+        assert_eq!(body, b"hello");
+    }
+
+    #[tokio::test]
+    async fn reads_chunked_body_with_extension_and_trailer() {
+        let response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: gzip, chunked\r\n\r\n\
+            5;name=value\r\nhello\r\n6\r\n world\r\n0\r\nX-Trailer: 1\r\n\r\n";
+        let (_, body) = parse(response, 1024).await.unwrap();
+        assert_eq!(body, b"hello world");
+    }
+
+    #[tokio::test]
+    async fn reads_body_until_close_without_length() {
+        let (_, body) = parse(b"HTTP/1.0 200 OK\r\n\r\nip=1.2.3.4", 1024).await.unwrap();
+        assert_eq!(body, b"ip=1.2.3.4");
+    }
+
+    #[tokio::test]
+    async fn skips_informational_responses() {
+        let response = b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n";
+        let (status, body) = parse(response, 1024).await.unwrap();
+        assert_eq!(status, 204);
+        assert!(body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn rejects_endless_informational_responses() {
+        let response = b"HTTP/1.1 100 Continue\r\n\r\n".repeat(MAX_HEAD_BYTES);
+        let result = parse(&response, 1024).await;
+        assert!(matches!(result, Err(CheckError::ResponseTooLarge(MAX_HEAD_BYTES))));
+    }
+
+    #[tokio::test]
+    async fn rejects_oversized_bodies() {
+        let result = parse(b"HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nhello world", 10).await;
+        assert!(matches!(result, Err(CheckError::ResponseTooLarge(10))));
+
+        let result = parse(b"HTTP/1.1 200 OK\r\n\r\nhello world", 10).await;
+        assert!(matches!(result, Err(CheckError::ResponseTooLarge(10))));
+
+        let response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n6\r\nhello \r\n5\r\nworld\r\n0\r\n\r\n";
+        let result = parse(response, 10).await;
+        assert!(matches!(result, Err(CheckError::ResponseTooLarge(10))));
+    }
+
+    #[tokio::test]
+    async fn rejects_chunk_size_that_would_overflow() {
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n1\r\na\r\n{:x}\r\n",
+            usize::MAX
+        );
+        let result = parse(response.as_bytes(), 1024).await;
+        assert!(matches!(result, Err(CheckError::ResponseTooLarge(1024))));
+    }
+
+    #[tokio::test]
+    async fn rejects_truncated_responses() {
+        let result = parse(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nhello", 1024).await;
+        assert!(matches!(result, Err(CheckError::InvalidHttpResponse(_))));
+
+        let result = parse(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhel", 1024).await;
+        assert!(matches!(result, Err(CheckError::InvalidHttpResponse(_))));
+
+        let result = parse(b"HTTP/1.1 200 OK\r\nContent-Len", 1024).await;
+        assert!(matches!(result, Err(CheckError::InvalidHttpResponse(_))));
+    }
+
+    #[tokio::test]
+    async fn rejects_malformed_responses() {
+        for response in [
+            &b"SSH-2.0-OpenSSH_9.6\r\n\r\n"[..],
+            b"HTTP/1.1 abc OK\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\nno colon here\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\nContent-Length: -1\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nzz\r\n",
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nabc\r\n0\r\n\r\n",
+        ] {
+            let result = parse(response, 1024).await;
+            assert!(matches!(result, Err(CheckError::InvalidHttpResponse(_))), "{:?}", String::from_utf8_lossy(response));
+        }
+    }
+}
@@ -0,0 +1,771 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Arc;
+
+use ipnetwork::{IpNetwork, NetworkSize};
+use serde_json::Value;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::exclude::{ip_to_u128, Exclusions};
+use crate::progress::Progress;
+
+// 校验报告中最多逐行列出的格式错误数，只保存这么多条
+const MAX_REPORTED_ERRORS: usize = 20;
+// 单个网段最多展开的地址数（一个 /8），更大的网段视为输入错误
+const MAX_NETWORK_SIZE: u128 = 1 << 24;
+
+// 一个待检测的代理地址，ip 已规范化，source 为来源文件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyTarget {
+    pub ip: IpAddr,
+    pub port: u16,
+    // 端口来自端口集合而不是输入本身，这类地址大多没有开放，检测前先做 TCP 预检
+    pub from_port_set: bool,
+    pub source: Arc<str>,
+}
+
+// 一行输入对应的检测地址：单个 IP 或一个网段，与一个或多个端口的组合
+// 按需展开，大网段不会一次性占用内存
+#[derive(Debug, Clone)]
+pub struct LineTargets {
+    network: IpNetwork,
+    ports: Vec<u16>,
+    from_port_set: bool,
+    source: Arc<str>,
+}
+
+impl LineTargets {
+    // IPv4 网段（/30 及更大）跳过网络地址与广播地址
+    pub fn iter(&self) -> impl Iterator<Item = ProxyTarget> + '_ {
+        let edges = match self.network {
+            IpNetwork::V4(v4) if v4.prefix() <= 30 => Some((IpAddr::V4(v4.network()), IpAddr::V4(v4.broadcast()))),
+            _ => None,
+        };
+        let hosts = self
+            .network
+            .iter()
+            .filter(move |&ip| edges.is_none_or(|(network, broadcast)| ip != network && ip != broadcast));
+        hosts.flat_map(move |ip| {
+            self.ports.iter().map(move |&port| ProxyTarget {
+                ip,
+                port,
+                from_port_set: self.from_port_set,
+                source: Arc::clone(&self.source),
+            })
+        })
+    }
+
+    // 输入为 CIDR 网段而不是单个 IP
+    pub fn is_range(&self) -> bool {
+        let max_prefix = if self.network.is_ipv4() { 32 } else { 128 };
+        self.network.prefix() < max_prefix
+    }
+}
+
+// 无法解析的输入行（行号从 1 开始）
+#[derive(Debug, Clone)]
+pub struct InputError {
+    pub source: Arc<str>,
+    pub line: usize,
+    pub content: String,
+    pub reason: String,
+}
+
+// 单个输入文件的统计
+#[derive(Debug, Default)]
+pub struct SourceStats {
+    pub path: Arc<str>,
+    // 解析出的地址数（只有 IP 的行按端口展开后计数）
+    pub entries: usize,
+    // 首次出现、实际参与检测的地址数
+    pub unique: usize,
+    // 与本文件或更早的文件重复
+    pub duplicates: usize,
+    // 命中保留网段、FireHOL 或排除列表，不做任何连接
+    pub excluded: usize,
+    pub malformed: usize,
+    // 空行、注释行与 CSV 表头
+    pub skipped: usize,
+}
+
+// 输入中的一项：待检测的地址或无法解析的行
+pub enum InputItem {
+    Target(ProxyTarget),
+    Invalid(InputError),
+}
+
+// 全部输入读完后的统计
+pub struct InputSummary {
+    pub sources: Vec<SourceStats>,
+    // (先出现的文件, 后出现的文件) -> 重复的地址数
+    pub overlaps: BTreeMap<(usize, usize), usize>,
+    // 排除原因 -> 地址数
+    pub excluded: BTreeMap<&'static str, usize>,
+    // 前 MAX_REPORTED_ERRORS 个格式错误行，随报告一起打印
+    pub errors: Vec<InputError>,
+}
+
+// 逐行解析代理列表，支持以下写法（可混用）:
+//   ip:port、[ipv6]:port
+//   ip 或 CIDR 网段（展开为网段内每个地址与 ports 中每个端口的组合）
+//   CSV: ip,port[,...]，第一行可以是表头（含 ip/host/address 列，可选 port 列）
+//   JSON Lines: {"ip": "1.2.3.4", "port": 443}，port 可为数字或字符串，缺省时使用 ports
+//   # 或 // 开头的注释行与空行会被跳过
+// IP 统一为标准写法：IPv4 映射的 IPv6 (::ffff:1.2.3.4) 转为 IPv4，去掉 IPv4 各段的前导零
+pub struct InputParser {
+    ports: Vec<u16>,
+    source: Arc<str>,
+    // CSV 表头中 ip 与 port 所在的列
+    columns: Option<(usize, Option<usize>)>,
+    first_line: bool,
+}
+
+impl InputParser {
+    pub fn new(ports: &[u16], source: Arc<str>) -> Self {
+        InputParser {
+            ports: ports.to_vec(),
+            source,
+            columns: None,
+            first_line: true,
+        }
+    }
+
+    // 返回 None 表示该行无需检测（空行、注释或表头）
+    pub fn parse_line(&mut self, line: &str) -> Option<Result<LineTargets, String>> {
+        let first_line = std::mem::replace(&mut self.first_line, false);
+        let line = line.trim_start_matches('\u{feff}').trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with("//") {
+            self.first_line = first_line;
+            return None;
+        }
+
+        if line.starts_with('{') {
+            return Some(self.parse_json(line));
+        }
+
+        if line.contains(',') {
+            if first_line {
+                if let Some(columns) = header_columns(line) {
+                    self.columns = Some(columns);
+                    return None;
+                }
+            }
+            return Some(self.parse_csv(line));
+        }
+
+        Some(self.parse_address(line))
+    }
+
+    // ip、ip:port、[ipv6]、[ipv6]:port 或 CIDR 网段
+    fn parse_address(&self, text: &str) -> Result<LineTargets, String> {
+        let text = text.trim();
+        let (ip, port) = if let Some(rest) = text.strip_prefix('[') {
+            let (ip, rest) = rest.split_once(']').ok_or(format!("invalid address '{}'", text))?;
+            match rest.strip_prefix(':') {
+                Some(port) => (ip, Some(port)),
+                None if rest.is_empty() => (ip, None),
+                None => return Err(format!("invalid address '{}'", text)),
+            }
+        } else if text.matches(':').count() == 1 {
+            let (ip, port) = text.split_once(':').unwrap_or((text, ""));
+            (ip, Some(port))
+        } else {
+            (text, None)
+        };
+
+        let network = parse_network(ip)?;
+        match port {
+            Some(port) => Ok(self.targets(network, parse_port(port)?)),
+            None => self.expand(network),
+        }
+    }
+
+    fn parse_csv(&self, line: &str) -> Result<LineTargets, String> {
+        let fields: Vec<&str> = line.split(',').map(|field| field.trim().trim_matches('"')).collect();
+        let (ip_column, port_column) = self.columns.unwrap_or((0, Some(1)));
+
+        let ip_field = fields.get(ip_column).copied().unwrap_or_default();
+        let port_field = port_column.and_then(|column| fields.get(column)).copied().unwrap_or_default();
+
+        // ip 列本身也可以写成 ip:port
+        if port_field.is_empty() {
+            return self.parse_address(ip_field);
+        }
+        Ok(self.targets(parse_network(ip_field)?, parse_port(port_field)?))
+    }
+
+    fn parse_json(&self, line: &str) -> Result<LineTargets, String> {
+        let value: Value = serde_json::from_str(line).map_err(|e| format!("invalid JSON: {}", e))?;
+        let ip = ["ip", "host", "address"]
+            .iter()
+            .find_map(|key| value.get(key).and_then(Value::as_str))
+            .ok_or("JSON object has no \"ip\" field")?;
+
+        match value.get("port") {
+            None | Some(Value::Null) => self.parse_address(ip),
+            Some(Value::Number(port)) => {
+                let port = port.as_u64().and_then(|p| u16::try_from(p).ok()).ok_or(format!("invalid port '{}'", port))?;
+                Ok(self.targets(parse_network(ip)?, port))
+            }
+            Some(Value::String(port)) => Ok(self.targets(parse_network(ip)?, parse_port(port)?)),
+            Some(other) => Err(format!("invalid port '{}'", other)),
+        }
+    }
+
+    // 输入中写明了端口
+    fn targets(&self, network: IpNetwork, port: u16) -> LineTargets {
+        LineTargets {
+            network,
+            ports: vec![port],
+            from_port_set: false,
+            source: Arc::clone(&self.source),
+        }
+    }
+
+    // 没有端口时展开为端口集合
+    fn expand(&self, network: IpNetwork) -> Result<LineTargets, String> {
+        if self.ports.is_empty() {
+            return Err("no port given and no default ports configured".to_string());
+        }
+        Ok(LineTargets {
+            network,
+            ports: self.ports.clone(),
+            from_port_set: true,
+            source: Arc::clone(&self.source),
+        })
+    }
+}
+
+// 第一行没有可解析的 IP 且包含 ip/host/address 列时视为表头
+fn header_columns(line: &str) -> Option<(usize, Option<usize>)> {
+    let names: Vec<String> = line
+        .split(',')
+        .map(|name| name.trim().trim_matches('"').to_ascii_lowercase())
+        .collect();
+    if names.iter().any(|name| parse_ip(name).is_ok()) {
+        return None;
+    }
+    let ip = names.iter().position(|name| matches!(name.as_str(), "ip" | "host" | "address"))?;
+    let port = names.iter().position(|name| name == "port");
+    Some((ip, port))
+}
+
+// 单个 IP 视为只含一个地址的网段
+fn parse_network(text: &str) -> Result<IpNetwork, String> {
+    let text = text.trim();
+    if !text.contains('/') {
+        let ip = parse_ip(text)?;
+        let prefix = if ip.is_ipv4() { 32 } else { 128 };
+        return IpNetwork::new(ip, prefix).map_err(|e| e.to_string());
+    }
+
+    let network: IpNetwork = text.parse().map_err(|_| format!("invalid CIDR '{}'", text))?;
+    let size = match network.size() {
+        NetworkSize::V4(size) => size as u128,
+        NetworkSize::V6(size) => size,
+    };
+    if size > MAX_NETWORK_SIZE {
+        return Err(format!("CIDR '{}' is too large to expand (more than {} addresses)", text, MAX_NETWORK_SIZE));
+    }
+    Ok(network)
+}
+
+fn parse_ip(text: &str) -> Result<IpAddr, String> {
+    let text = text.trim();
+    let text = text.strip_prefix('[').and_then(|t| t.strip_suffix(']')).unwrap_or(text);
+    let ip = match text.parse::<IpAddr>() {
+        Ok(ip) => ip,
+        Err(_) => parse_ipv4_leading_zeros(text).ok_or(format!("invalid IP address '{}'", text))?,
+    };
+    Ok(ip.to_canonical())
+}
+
+// 010.001.002.003 这类写法按十进制处理（标准库会拒绝前导零）
+fn parse_ipv4_leading_zeros(text: &str) -> Option<IpAddr> {
+    let octets: Vec<u8> = text
+        .split('.')
+        .map(|part| {
+            if part.is_empty() || part.len() > 3 || !part.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            part.parse::<u8>().ok()
+        })
+        .collect::<Option<_>>()?;
+    let octets: [u8; 4] = octets.try_into().ok()?;
+    Some(IpAddr::V4(Ipv4Addr::from(octets)))
+}
+
+fn parse_port(text: &str) -> Result<u16, String> {
+    match text.trim().parse::<u16>() {
+        Ok(port) if port > 0 => Ok(port),
+        _ => Err(format!("invalid port '{}'", text.trim())),
+    }
+}
+
+// 标准输入的写法，可与文件混用
+pub const STDIN: &str = "-";
+
+// 展开 glob 模式（Data/*.txt），普通路径与 "-" 原样保留；重复的路径只读取一次
+pub fn expand_inputs(patterns: &[String]) -> io::Result<Vec<String>> {
+    let mut paths: Vec<String> = Vec::new();
+    for pattern in patterns {
+        if pattern == STDIN || !pattern.contains(['*', '?', '[']) {
+            if !paths.contains(pattern) {
+                paths.push(pattern.clone());
+            }
+            continue;
+        }
+
+        let entries = glob::glob(pattern)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("invalid glob '{}': {}", pattern, e)))?;
+        let mut matched = 0;
+        for entry in entries {
+            let path = entry.map_err(io::Error::from)?.to_string_lossy().into_owned();
+            matched += 1;
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+        if matched == 0 {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("no files match '{}'", pattern)));
+        }
+    }
+    Ok(paths)
+}
+
+// 已打开的输入，name 用于报告与结果中的 source 字段
+pub struct InputSource {
+    name: Arc<str>,
+    reader: Box<dyn BufRead + Send>,
+    // 普通文件可以重新打开再读一遍；FIFO、/dev/stdin、进程替换等只能读一次
+    regular: bool,
+}
+
+impl InputSource {
+    pub fn is_regular_file(&self) -> bool {
+        self.regular
+    }
+}
+
+// 扫描开始前打开全部输入，路径错误时立即失败
+pub fn open_inputs(patterns: &[String]) -> io::Result<Vec<InputSource>> {
+    expand_inputs(patterns)?
+        .into_iter()
+        .map(|path| {
+            if path == STDIN {
+                return Ok(InputSource {
+                    name: Arc::from("stdin"),
+                    reader: Box::new(BufReader::new(io::stdin())),
+                    regular: false,
+                });
+            }
+            let file = File::open(&path).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path, e)))?;
+            let regular = fs::metadata(&path).is_ok_and(|metadata| metadata.is_file());
+            Ok(InputSource {
+                name: Arc::from(path),
+                reader: Box::new(BufReader::new(file)),
+                regular,
+            })
+        })
+        .collect()
+}
+
+// 逐行读取所有输入并交给 emit，emit 返回 false 时停止读取；被排除的地址在去重前丢弃
+// 按 (ip, port) 去重：已输出的地址按端口记录为连续区间，网段之间、网段与单个地址之间的重叠都会去掉，
+// 一个 /8 只占一个区间，内存随输入行数而不是展开的地址数增长；dedup = false 时不保存任何状态
+pub fn read_inputs(
+    sources: Vec<InputSource>,
+    ports: &[u16],
+    dedup: bool,
+    exclusions: &Exclusions,
+    mut emit: impl FnMut(InputItem) -> bool,
+) -> io::Result<InputSummary> {
+    let mut summary = InputSummary {
+        sources: Vec::new(),
+        overlaps: BTreeMap::new(),
+        excluded: BTreeMap::new(),
+        errors: Vec::new(),
+    };
+    let mut seen = EmittedRanges::default();
+
+    for (index, source) in sources.into_iter().enumerate() {
+        let mut parser = InputParser::new(ports, Arc::clone(&source.name));
+        let mut stats = SourceStats {
+            path: Arc::clone(&source.name),
+            ..SourceStats::default()
+        };
+        let mut stopped = false;
+
+        'lines: for (line_index, line) in source.reader.lines().enumerate() {
+            let line = line.map_err(|e| io::Error::new(e.kind(), format!("{}: {}", source.name, e)))?;
+            let targets = match parser.parse_line(&line) {
+                None => {
+                    stats.skipped += 1;
+                    continue;
+                }
+                Some(Ok(line_targets)) => line_targets,
+                Some(Err(reason)) => {
+                    stats.malformed += 1;
+                    let error = InputError {
+                        source: Arc::clone(&source.name),
+                        line: line_index + 1,
+                        content: line,
+                        reason,
+                    };
+                    if summary.errors.len() < MAX_REPORTED_ERRORS {
+                        summary.errors.push(error.clone());
+                    }
+                    if !emit(InputItem::Invalid(error)) {
+                        stopped = true;
+                        break;
+                    }
+                    continue;
+                }
+            };
+
+            let from_range = targets.is_range();
+            for target in targets.iter() {
+                stats.entries += 1;
+                if let Some(reason) = exclusions.reason(target.ip, from_range) {
+                    stats.excluded += 1;
+                    *summary.excluded.entry(reason).or_insert(0) += 1;
+                    continue;
+                }
+                if dedup {
+                    if let Some(first) = seen.find(target.ip, target.port) {
+                        stats.duplicates += 1;
+                        if first != index {
+                            *summary.overlaps.entry((first, index)).or_insert(0) += 1;
+                        }
+                        continue;
+                    }
+                    seen.insert(target.ip, target.port, index);
+                }
+                stats.unique += 1;
+                if !emit(InputItem::Target(target)) {
+                    stopped = true;
+                    break 'lines;
+                }
+            }
+        }
+
+        summary.sources.push(stats);
+        if stopped {
+            break;
+        }
+    }
+
+    Ok(summary)
+}
+
+// 已输出的地址，每个端口一组互不重叠的区间：(是否 IPv6, 起始) -> (结束, 第一次出现的文件序号)
+// 连续输出的地址合并为一个区间，只有来自同一文件的相邻地址才合并，以便统计文件之间的重叠
+#[derive(Default)]
+struct EmittedRanges {
+    ports: HashMap<u16, Ranges>,
+}
+
+type Ranges = BTreeMap<(bool, u128), (u128, usize)>;
+
+impl EmittedRanges {
+    fn find(&self, ip: IpAddr, port: u16) -> Option<usize> {
+        let key = (ip.is_ipv6(), ip_to_u128(ip));
+        let (&(v6, _), &(end, source)) = self.ports.get(&port)?.range(..=key).next_back()?;
+        (v6 == key.0 && end >= key.1).then_some(source)
+    }
+
+    // 调用前已确认 ip 不在任何区间内
+    fn insert(&mut self, ip: IpAddr, port: u16, source: usize) {
+        let ranges = self.ports.entry(port).or_default();
+        let (v6, value) = (ip.is_ipv6(), ip_to_u128(ip));
+        let mut start = value;
+        let mut end = value;
+
+        if let Some((&(prev_v6, prev_start), &(prev_end, prev_source))) = ranges.range(..(v6, value)).next_back() {
+            if prev_v6 == v6 && prev_source == source && prev_end.checked_add(1) == Some(value) {
+                ranges.remove(&(v6, prev_start));
+                start = prev_start;
+            }
+        }
+        if let Some(next) = value.checked_add(1) {
+            if let Some(&(next_end, next_source)) = ranges.get(&(v6, next)) {
+                if next_source == source {
+                    ranges.remove(&(v6, next));
+                    end = next_end;
+                }
+            }
+        }
+        ranges.insert((v6, start), (end, source));
+    }
+}
+
+// 在后台线程中读取输入，通过有界 channel 交给扫描任务；扫描跟不上时读取会暂停
+// 传入 progress 时每读到一项就累加进度总数，全部读完后进度才显示百分比与 ETA；
+// 文件输入的总数已由预读得出，此时传 None
+pub fn spawn_reader(
+    sources: Vec<InputSource>,
+    ports: Vec<u16>,
+    dedup: bool,
+    exclusions: Exclusions,
+    capacity: usize,
+    progress: Option<Arc<Progress>>,
+) -> (mpsc::Receiver<InputItem>, JoinHandle<io::Result<InputSummary>>) {
+    let (tx, rx) = mpsc::channel(capacity.max(1));
+    let handle = tokio::task::spawn_blocking(move || {
+        let summary = read_inputs(sources, &ports, dedup, &exclusions, |item| {
+            if let Some(progress) = &progress {
+                progress.add_total(1);
+            }
+            tx.blocking_send(item).is_ok()
+        });
+        if let Some(progress) = &progress {
+            progress.finish_input();
+        }
+        summary
+    });
+    (rx, handle)
+}
+
+impl InputSummary {
+    // 有效地址（去重后）与格式错误行的总数，即扫描处理的条目数
+    pub fn total_inputs(&self) -> usize {
+        self.sources.iter().map(|s| s.unique + s.malformed).sum()
+    }
+
+    // 输入校验与去重报告
+    pub fn print_report(&self) {
+        let unique: usize = self.sources.iter().map(|s| s.unique).sum();
+        let malformed: usize = self.sources.iter().map(|s| s.malformed).sum();
+        let duplicates: usize = self.sources.iter().map(|s| s.duplicates).sum();
+        eprintln!(
+            "Read {} unique proxies from {} input(s) ({} malformed, {} duplicates removed)",
+            unique,
+            self.sources.len(),
+            malformed,
+            duplicates
+        );
+        for source in &self.sources {
+            eprintln!(
+                "  {}: {} entries, {} unique, {} duplicates, {} excluded, {} malformed, {} comment/header/blank lines",
+                source.path, source.entries, source.unique, source.duplicates, source.excluded, source.malformed, source.skipped
+            );
+        }
+        if !self.excluded.is_empty() {
+            let reasons: Vec<String> = self.excluded.iter().map(|(reason, count)| format!("{} {}", count, reason)).collect();
+            eprintln!("Skipped {} excluded addresses without connecting ({})", self.excluded.values().sum::<usize>(), reasons.join(", "));
+        }
+        for (&(first, second), count) in &self.overlaps {
+            eprintln!(
+                "  overlap: {} entries of {} already in {}",
+                count, self.sources[second].path, self.sources[first].path
+            );
+        }
+        for error in &self.errors {
+            eprintln!("⚠️ {}:{}: {} ({})", error.source, error.line, error.reason, error.content.trim());
+        }
+        if malformed > MAX_REPORTED_ERRORS {
+            eprintln!("⚠️ {} more malformed lines not shown (use --rejected to list them all)", malformed - MAX_REPORTED_ERRORS);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 逐行解析，展开为 "ip:port" 列表；None 表示被跳过的行
+    fn parse_lines(ports: &[u16], lines: &[&str]) -> Vec<Option<Result<Vec<String>, String>>> {
+        let mut parser = InputParser::new(ports, Arc::from("test"));
+        lines
+            .iter()
+            .map(|line| {
+                parser.parse_line(line).map(|result| {
+                    result.map(|targets| {
+                        targets.iter().map(|t| format!("{}:{}", t.ip, t.port)).collect()
+                    })
+                })
+            })
+            .collect()
+    }
+
+    fn parse_one(ports: &[u16], line: &str) -> Result<Vec<String>, String> {
+        parse_lines(ports, &[line]).remove(0).expect("line should not be skipped")
+    }
+
+    #[test]
+    fn parses_ip_port_forms() {
+        assert_eq!(parse_one(&[443], "1.2.3.4:8443").unwrap(), ["1.2.3.4:8443"]);
+        assert_eq!(parse_one(&[443], "[2001:db8::1]:443").unwrap(), ["2001:db8::1:443"]);
+        assert_eq!(parse_one(&[443, 2053], "[2001:db8::1]").unwrap(), ["2001:db8::1:443", "2001:db8::1:2053"]);
+        assert_eq!(parse_one(&[443, 2053], "1.2.3.4").unwrap(), ["1.2.3.4:443", "1.2.3.4:2053"]);
+        assert_eq!(parse_one(&[443], "2001:db8::1").unwrap(), ["2001:db8::1:443"]);
+    }
+
+    #[test]
+    fn explicit_ports_skip_the_precheck() {
+        let mut parser = InputParser::new(&[443], Arc::from("test"));
+        let explicit = parser.parse_line("1.2.3.4:443").unwrap().unwrap();
+        let expanded = parser.parse_line("1.2.3.4").unwrap().unwrap();
+        assert!(explicit.iter().all(|t| !t.from_port_set));
+        assert!(expanded.iter().all(|t| t.from_port_set));
+    }
+
+    #[test]
+    fn normalizes_addresses() {
+        assert_eq!(parse_one(&[443], "010.001.002.003:443").unwrap(), ["10.1.2.3:443"]);
+        assert_eq!(parse_one(&[443], "[::ffff:1.2.3.4]:443").unwrap(), ["1.2.3.4:443"]);
+        assert_eq!(parse_one(&[443], "::ffff:1.2.3.4").unwrap(), ["1.2.3.4:443"]);
+        assert_eq!(parse_one(&[443], "\u{feff}1.2.3.4:443").unwrap(), ["1.2.3.4:443"]);
+    }
+
+    #[test]
+    fn expands_cidr_without_network_and_broadcast() {
+        assert_eq!(parse_one(&[443], "10.0.0.0/30").unwrap(), ["10.0.0.1:443", "10.0.0.2:443"]);
+        assert_eq!(parse_one(&[443], "10.0.0.0/31").unwrap(), ["10.0.0.0:443", "10.0.0.1:443"]);
+        assert_eq!(parse_one(&[443], "10.0.0.7/32").unwrap(), ["10.0.0.7:443"]);
+        assert_eq!(parse_one(&[80], "10.0.0.4/30:8080").unwrap(), ["10.0.0.5:8080", "10.0.0.6:8080"]);
+        assert!(parse_one(&[443], "10.0.0.0/7").is_err());
+    }
+
+    #[test]
+    fn skips_comments_and_blank_lines() {
+        let parsed = parse_lines(&[443], &["", "   ", "# comment", "// comment", "1.2.3.4:443"]);
+        assert!(parsed[..4].iter().all(Option::is_none));
+        assert_eq!(parsed[4].clone().unwrap().unwrap(), ["1.2.3.4:443"]);
+    }
+
+    #[test]
+    fn detects_csv_header_after_comments() {
+        let parsed = parse_lines(&[443], &["# export", "country,address,port", "DE,1.2.3.4,8443", "FR,5.6.7.8,"]);
+        assert!(parsed[1].is_none());
+        assert_eq!(parsed[2].clone().unwrap().unwrap(), ["1.2.3.4:8443"]);
+        assert_eq!(parsed[3].clone().unwrap().unwrap(), ["5.6.7.8:443"]);
+    }
+
+    #[test]
+    fn parses_csv_without_header() {
+        let parsed = parse_lines(&[443], &["1.2.3.4,8443,DE", "\"5.6.7.8\",\"443\"", "ip,port"]);
+        assert_eq!(parsed[0].clone().unwrap().unwrap(), ["1.2.3.4:8443"]);
+        assert_eq!(parsed[1].clone().unwrap().unwrap(), ["5.6.7.8:443"]);
+        // 只有第一行可以是表头
+        assert!(parsed[2].clone().unwrap().is_err());
+    }
+
+    #[test]
+    fn parses_json_lines() {
+        assert_eq!(parse_one(&[443], r#"{"ip": "1.2.3.4", "port": 8443}"#).unwrap(), ["1.2.3.4:8443"]);
+        assert_eq!(parse_one(&[443], r#"{"host": "1.2.3.4", "port": "2053"}"#).unwrap(), ["1.2.3.4:2053"]);
+        assert_eq!(parse_one(&[443], r#"{"address": "1.2.3.4:80"}"#).unwrap(), ["1.2.3.4:80"]);
+        assert_eq!(parse_one(&[443], r#"{"ip": "1.2.3.4", "port": null}"#).unwrap(), ["1.2.3.4:443"]);
+        assert!(parse_one(&[443], r#"{"port": 443}"#).is_err());
+        assert!(parse_one(&[443], r#"{"ip": "1.2.3.4", "port": 70000}"#).is_err());
+        assert!(parse_one(&[443], r#"{"ip": "1.2.3.4", "port": true}"#).is_err());
+        assert!(parse_one(&[443], r#"{"ip": "1.2.3.4""#).is_err());
+    }
+
+    fn read(inputs: &[&'static str]) -> (Vec<String>, InputSummary) {
+        let sources = inputs
+            .iter()
+            .enumerate()
+            .map(|(index, data)| InputSource {
+                name: Arc::from(format!("input{}", index)),
+                reader: Box::new(data.as_bytes()),
+                regular: false,
+            })
+            .collect();
+        let mut targets = Vec::new();
+        let summary = read_inputs(sources, &[443], true, &Exclusions::default(), |item| {
+            if let InputItem::Target(t) = item {
+                targets.push(format!("{}:{}", t.ip, t.port));
+            }
+            true
+        })
+        .unwrap();
+        (targets, summary)
+    }
+
+    #[test]
+    fn deduplicates_addresses_across_inputs() {
+        let (targets, summary) = read(&["1.2.3.4:443\n1.2.3.4\nbad\n", "# list\n1.2.3.4:443\n5.6.7.8:443\n"]);
+        assert_eq!(targets, ["1.2.3.4:443", "5.6.7.8:443"]);
+        assert_eq!(summary.total_inputs(), 3);
+        assert_eq!(summary.sources[0].duplicates, 1);
+        assert_eq!(summary.sources[1].skipped, 1);
+        assert_eq!(summary.overlaps.get(&(0, 1)), Some(&1));
+        assert_eq!(summary.errors.len(), 1);
+        assert_eq!(summary.errors[0].line, 3);
+    }
+
+    #[test]
+    fn deduplicates_overlapping_ranges_and_addresses() {
+        let (targets, summary) = read(&["10.0.0.0/30\n10.0.0.1\n", "10.0.0.3/30\n"]);
+        assert_eq!(targets, ["10.0.0.1:443", "10.0.0.2:443"]);
+        assert_eq!(summary.sources[0].duplicates, 1);
+        assert_eq!(summary.sources[1].duplicates, 2);
+        assert_eq!(summary.overlaps.get(&(0, 1)), Some(&2));
+
+        // 单个地址在网段之前、网段部分重叠，以及网段跳过的网络地址之后单独列出
+        let (targets, summary) = read(&["10.0.0.5\n10.0.0.0/29\n10.0.0.0\n", "10.0.0.4/30\n10.0.0.8/29\n"]);
+        assert_eq!(
+            targets,
+            [
+                "10.0.0.5:443", "10.0.0.1:443", "10.0.0.2:443", "10.0.0.3:443", "10.0.0.4:443", "10.0.0.6:443", "10.0.0.0:443",
+                "10.0.0.9:443", "10.0.0.10:443", "10.0.0.11:443", "10.0.0.12:443", "10.0.0.13:443", "10.0.0.14:443",
+            ]
+        );
+        assert_eq!(summary.sources[0].duplicates, 1);
+        assert_eq!(summary.overlaps.get(&(0, 1)), Some(&2));
+    }
+
+    #[test]
+    fn deduplicates_per_port() {
+        let (targets, _) = read(&["1.2.3.4:80\n1.2.3.0/30,443\n", "1.2.3.4/31,80\n1.2.3.1:443\n"]);
+        assert_eq!(targets, ["1.2.3.4:80", "1.2.3.1:443", "1.2.3.2:443", "1.2.3.5:80"]);
+    }
+
+    #[test]
+    fn emitted_ranges_stay_compact() {
+        let mut seen = EmittedRanges::default();
+        let network: IpNetwork = "10.0.0.0/16".parse().unwrap();
+        for ip in network.iter() {
+            assert_eq!(seen.find(ip, 443), None);
+            seen.insert(ip, 443, 0);
+        }
+        assert_eq!(seen.ports[&443].len(), 1);
+        // 从两侧接上的地址合并进同一区间，其他文件的地址单独成段
+        seen.insert("10.1.0.0".parse().unwrap(), 443, 0);
+        seen.insert("9.255.255.255".parse().unwrap(), 443, 0);
+        seen.insert("10.1.0.1".parse().unwrap(), 443, 1);
+        assert_eq!(seen.ports[&443].len(), 2);
+        assert_eq!(seen.find("10.0.128.0".parse().unwrap(), 443), Some(0));
+        assert_eq!(seen.find("10.1.0.1".parse().unwrap(), 443), Some(1));
+        assert_eq!(seen.find("10.1.0.2".parse().unwrap(), 443), None);
+        assert_eq!(seen.find("10.0.0.1".parse().unwrap(), 80), None);
+        // IPv4 与 IPv6 分开比较
+        assert_eq!(seen.find("::a00:1".parse().unwrap(), 443), None);
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        for line in ["1.2.3.4:0", "1.2.3.4:65536", "1.2.3.4:http", "1.2.3.256", "1.2.3", "0001.2.3.4", "[::1", "[::1]x", "example.com:443"] {
+            assert!(parse_one(&[443], line).is_err(), "{}", line);
+        }
+        assert!(parse_one(&[], "1.2.3.4").is_err());
+    }
+
+    #[test]
+    fn only_regular_files_can_be_read_twice() {
+        let path = std::env::temp_dir().join(format!("cekproxy-input-{}.txt", std::process::id()));
+        fs::write(&path, "1.2.3.4:80\n").unwrap();
+        let path = path.to_string_lossy().to_string();
+        let sources = open_inputs(&[path.clone(), STDIN.to_string()]).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(sources[0].is_regular_file());
+        assert!(!sources[1].is_regular_file());
+        #[cfg(unix)]
+        assert!(!open_inputs(&["/dev/null".to_string()]).unwrap()[0].is_regular_file());
+    }
+}
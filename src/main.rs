@@ -1,41 +1,49 @@
+mod checker;
+mod concurrency;
+mod cli;
+mod config;
+mod db;
+mod error;
+mod exclude;
+mod geo;
+mod http;
+mod input;
+mod output;
+mod politeness;
+mod progress;
+mod report;
+mod resolver;
+mod retry;
+mod tls;
+
 use std::collections::HashSet;
-use std::env;
-use std::fs::{self, File};
-use std::io::{self, BufRead, BufReader, Write}; // Read dihapus karena AsyncReadExt akan digunakan
-use std::net::IpAddr;
-use std::path::Path;
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
 
-use deadpool_postgres::{Config, ManagerConfig, Pool, RecyclingMethod, Runtime};
+use clap::Parser;
+use deadpool_postgres::Pool;
 use futures::StreamExt;
-use ipnetwork::IpNetwork;
-use maxminddb::{geoip2, Reader};
-use native_tls::TlsConnector as NativeTlsConnector; // Renamed to avoid conflict
-use serde_json::Value;
-use tokio::io::{AsyncReadExt, AsyncWriteExt}; // Untuk read_exact, write_all async
-use tokio::net::TcpStream; // TcpStream async dari Tokio
-use tokio_native_tls::TlsConnector as TokioTlsConnector; // Konektor TLS async
-use tokio_postgres::NoTls;
-
-const IP_RESOLVER: &str = "speed.cloudflare.com";
-const PATH_RESOLVER: &str = "/meta";
-const PROXY_FILE: &str = "Data/emeliaProxyIP15AGS.txt"; //input
-const OUTPUT_FILE: &str = "Data/alive.txt";
-const COUNTRY_DB: &str = "Data/GeoLite2-Country.mmdb";
-const CITY_DB: &str = "Data/GeoLite2-City.mmdb";
-const ASN_DB: &str = "Data/GeoLite2-ASN.mmdb";
-const ANONYMOUS_IP_DB: &str = "Data/GeoIP2-Anonymous-IP.mmdb";
-const ABUSE_IP_FILE: &str = "Data/abuseips.txt";
-const FIREHOL_CIDR_FILE: &str = "Data/firehol_cidr.txt";
-const MAX_CONCURRENT: usize = 175;
-const TIMEOUT_SECONDS: u64 = 9;
+use serde::Serialize;
+
+use checker::{CfMeta, Checker, Timings};
+use concurrency::ConcurrencyLimiter;
+use cli::{CheckArgs, Cli, Command, DbArgs, DbCommand, EnrichArgs};
+use config::Config;
+use db::{batch_insert_proxies, cleanup_old_proxies, create_pg_pool, migrate_schema, test_database_connection};
+use error::CheckError;
+use exclude::Exclusions;
+use geo::{GeoDatabases, GeoInfo};
+use input::{open_inputs, read_inputs, spawn_reader, InputItem, InputParser, InputSource, ProxyTarget};
+use output::{build_sinks, csv_line, OutputSink, RejectedWriter};
+use politeness::{HostLimiter, HostQueue, HostSlot, RateLimiter};
+use progress::Progress;
+use report::ScanStats;
 
 // Define a custom error type that implements Send + Sync
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
 
 // 代理数据结构
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(test, derive(Default))]
 struct ProxyData {
     ip: String,
     port: u16,
@@ -45,514 +53,437 @@ struct ProxyData {
     city_name: String,
     asn_number: String,
     org_name: String,
+    #[serde(flatten)]
+    timings: Timings,
+    // 经代理访问 Cloudflare 时看到的出口信息（colo、出口 IP 等）
+    cf: CfMeta,
+    // 出口 IP（cf.client_ip）是否与入口 IP 不同，以及出口 IP 的地理信息
+    exit_differs: bool,
+    exit_geo: GeoInfo,
+    // 黑名单命中原因，未命中为空（按 filters.target 未参与过滤的一侧也会记录）
+    entry_blocklist: String,
+    exit_blocklist: String,
+    // 提供结果的 resolver 共尝试了几次（含成功的一次）
+    attempts: u32,
+    // 该代理来自哪个输入文件（多个文件重复时为第一个）
+    source: String,
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    println!("Starting proxy scanner...");
-
-    // Create output directory if it doesn't exist
-    if let Some(parent) = Path::new(OUTPUT_FILE).parent() {
-        fs::create_dir_all(parent)?;
+impl ProxyData {
+    // CSV 格式: ip,port,国家代码,国家名,城市代码,城市名,ASN编号,组织名,各阶段耗时(ms),Cloudflare 出口信息,出口 IP 地理信息,黑名单,尝试次数,来源文件
+    fn csv_fields(&self) -> Vec<String> {
+        vec![
+            self.ip.clone(),
+            self.port.to_string(),
+            self.country_code.clone(),
+            self.country_name.clone(),
+            self.city_code.clone(),
+            self.city_name.clone(),
+            self.asn_number.clone(),
+            self.org_name.clone(),
+            self.timings.connect_ms.to_string(),
+            self.timings.tls_handshake_ms.to_string(),
+            self.timings.ttfb_ms.to_string(),
+            self.timings.total_ms.to_string(),
+            self.cf.client_ip.clone(),
+            self.cf.colo.clone(),
+            self.cf.asn.to_string(),
+            self.cf.as_organization.clone(),
+            self.cf.country.clone(),
+            self.cf.city.clone(),
+            self.cf.region.clone(),
+            self.cf.http_protocol.clone(),
+            self.cf.tls_version.clone(),
+            self.exit_differs.to_string(),
+            self.exit_geo.country_code.clone(),
+            self.exit_geo.country_name.clone(),
+            self.exit_geo.city_code.clone(),
+            self.exit_geo.city_name.clone(),
+            self.exit_geo.asn_number.clone(),
+            self.exit_geo.org_name.clone(),
+            self.entry_blocklist.clone(),
+            self.exit_blocklist.clone(),
+            self.attempts.to_string(),
+            self.source.clone(),
+        ]
     }
+}
 
-    // Initialize GeoIP database readers
-    let country_reader = Arc::new(Reader::open_readfile(COUNTRY_DB)?);
-    println!("Loaded Country database: {}", COUNTRY_DB);
+// 单次检测的元数据，随结果一起输出（不写入数据库）
+#[derive(Debug, Clone, Serialize)]
+struct CheckMeta {
+    checked_at: chrono::DateTime<chrono::Utc>,
+    // 提供出口信息的 resolver，以及本次通过/使用的 resolver 数量
+    resolver: String,
+    resolvers_passed: usize,
+    resolvers_checked: usize,
+}
 
-    let city_reader = match Reader::open_readfile(CITY_DB) {
-        Ok(reader) => {
-            println!("Loaded City database: {}", CITY_DB);
-            Some(Arc::new(reader))
-        }
-        Err(e) => {
-            eprintln!("Warning: Could not load City database ({}): {}. City info will show as '未知'.", CITY_DB, e);
-            None
-        }
-    };
+// 扫描过程中各任务共享的状态
+struct ScanContext {
+    checker: Checker,
+    sinks: Mutex<Vec<Box<dyn OutputSink>>>,
+    // stdout 被结果流占用时不再打印每条存活日志
+    log_live: bool,
+    rejected: Option<Mutex<RejectedWriter>>,
+    // 扫描统计，结束时生成 scan_report.json
+    stats: Mutex<ScanStats>,
+    progress: Arc<Progress>,
+    limiter: Arc<ConcurrencyLimiter>,
+    hosts: HostLimiter,
+    proxy_data_batch: Mutex<Vec<ProxyData>>,
+    batch_counter: Mutex<usize>,
+    // None 表示 --no-db 模式，只写输出文件
+    pg_pool: Option<Arc<Pool>>,
+    batch_time: chrono::DateTime<chrono::Utc>,
+}
 
-    // Initialize ASN database reader (optional)
-    let asn_reader = match Reader::open_readfile(ASN_DB) {
-        Ok(reader) => {
-            println!("Loaded ASN database: {}", ASN_DB);
-            Some(Arc::new(reader))
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let mut config = Config::load(cli.config.as_deref())?;
+
+    match cli.into_command() {
+        Command::Scan(args) => {
+            config.apply_scan_args(&args);
+            config.validate_scan()?;
+            run_scan(config).await
         }
-        Err(e) => {
-            eprintln!("Warning: Could not load ASN database ({}): {}. ASN info will show as empty.", ASN_DB, e);
-            None
+        Command::Check(args) => {
+            config.apply_probe_args(&args.probe);
+            config.apply_data_args(&args.data);
+            run_check(args, config).await
         }
-    };
-
-    // Initialize Anonymous IP database reader (optional)
-    let anonymous_reader = match Reader::open_readfile(ANONYMOUS_IP_DB) {
-        Ok(reader) => {
-            println!("Loaded Anonymous IP database: {}", ANONYMOUS_IP_DB);
-            Some(Arc::new(reader))
+        Command::Enrich(args) => {
+            config.apply_data_args(&args.data);
+            run_enrich(args, config)
         }
-        Err(e) => {
-            eprintln!("Warning: Could not load Anonymous IP database ({}): {}. Anonymous IP filtering will be disabled.", ANONYMOUS_IP_DB, e);
-            None
+        Command::Db(args) => {
+            config.apply_database_args(&args.database);
+            run_db(args, config).await
         }
-    };
+    }
+}
 
-    // Load AbuseIPDB blacklist
-    let abuse_ips = Arc::new(load_abuse_ips(ABUSE_IP_FILE));
+async fn run_scan(config: Config) -> Result<()> {
+    eprintln!("Starting proxy scanner...");
 
-    // Load FireHOL CIDR blocklist
-    let firehol_cidrs = Arc::new(load_firehol_cidrs(FIREHOL_CIDR_FILE));
+    // Initialize GeoIP database readers and blocklists
+    let geo = GeoDatabases::load(&config.data, &config.filters)?;
 
-    // Clear output file before starting
-    // File::create akan mengosongkan file jika sudah ada atau membuatnya jika belum
-    File::create(OUTPUT_FILE)?;
-    println!("File {} has been cleared or created before scanning process started.", OUTPUT_FILE);
+    // Open output sinks before starting; file sinks are truncated here
+    let sinks = build_sinks(&config.output)?;
+    if sinks.is_empty() {
+        return Err("No output configured. Enable at least one of the CSV file, --jsonl or --stdout".into());
+    }
+    let rejected = match &config.output.rejected {
+        Some(path) => Some(Mutex::new(RejectedWriter::create(path)?)),
+        None => None,
+    };
 
-    // Read proxy list from file
-    let proxies = match read_proxy_file(PROXY_FILE) {
-        Ok(proxies) => proxies,
+    // Open every input up front so a missing file fails before any connection is made;
+    // the lines themselves are streamed while scanning
+    let mut sources = match open_inputs(&config.scan.input) {
+        Ok(sources) => sources,
         Err(e) => {
             eprintln!("Error reading proxy file: {}", e);
             return Err(e.into());
         }
     };
+    eprintln!("Streaming proxies from {} input(s)", sources.len());
+    let exclusions = Exclusions::load(&config.scan, &config.filters, &geo.firehol_cidrs)?;
+
+    // 文件输入先完整读一遍（不保存地址）：扫描开始前报告格式错误，并得到准确的总数用于进度与 ETA
+    // 标准输入、FIFO 等只能读一次，任一输入不是普通文件时全部边读边扫描，报告在扫描结束后打印
+    let streamed = !sources.iter().all(InputSource::is_regular_file);
+    let validated = if streamed {
+        None
+    } else {
+        let summary = read_inputs(sources, &config.scan.ports, config.scan.dedup, &exclusions, |_| true)?;
+        summary.print_report();
+        sources = open_inputs(&config.scan.input)?;
+        Some(summary)
+    };
 
-    println!("Loaded {} proxies from file", proxies.len());
+    let mut checker = Checker::new(&config.probe, &config.tls, &config.retry, geo).await?;
+    checker.rate_limit = RateLimiter::new(config.scan.rate_limit);
+    if let Some(rate_limit) = &checker.rate_limit {
+        eprintln!("⚙️ Rate limit: {} connections/s", rate_limit.rate());
+    }
+    // HostLimiter 按检测计数，一次检测同时连多个 resolver 会超出上限
+    checker.sequential = config.scan.per_ip_concurrent > 0 || config.scan.per_subnet_concurrent > 0;
+
+    let pg_pool = if config.database.enabled {
+        // Initialize PostgreSQL connection pool (required unless --no-db)
+        eprintln!("🔌 Initializing PostgreSQL connection...");
+        let pool = match create_pg_pool(config.database.url.as_deref()) {
+            Ok(pool) => pool,
+            Err(e) => {
+                eprintln!("❌ Failed to create PostgreSQL connection pool: {}", e);
+                eprintln!("💡 Please configure DATABASE_URL and ensure database is accessible, or run with --no-db");
+                std::process::exit(1);
+            }
+        };
 
-    // Get original IP (without proxy)
-    let original_ip_data = match check_connection(IP_RESOLVER, PATH_RESOLVER, None).await {
-        Ok(data) => data,
-        Err(e) => {
-            eprintln!("Failed to get original IP info: {}", e);
-            // Consider if you want to exit here. If speed.cloudflare.com is down, no checks can be done.
-            return Err(e.into());
+        // Test database connection (required unless --no-db)
+        if let Err(e) = test_database_connection(&pool).await {
+            eprintln!("❌ Database connection test failed: {}", e);
+            eprintln!("💡 Please check: DATABASE_URL, network connectivity, and run schema.sql");
+            std::process::exit(1);
         }
-    };
-
-    let original_ip = match original_ip_data.get("clientIp") {
-        Some(Value::String(ip)) => ip.clone(),
-        _ => {
-            eprintln!("Failed to extract original client IP from response: {:?}", original_ip_data);
-            return Err("Failed to extract original client IP".into());
+        // scan 会写入新增的列，写入前补齐表结构
+        if let Err(e) = migrate_schema(&pool).await {
+            eprintln!("❌ Database schema migration failed: {}", e);
+            std::process::exit(1);
         }
+        eprintln!("✅ Database ready for sync");
+        Some(Arc::new(pool))
+    } else {
+        eprintln!("⚠️ PostgreSQL sync disabled, results will only be written to the configured outputs");
+        None
     };
 
-    println!("Original IP: {}", original_ip);
+    let limiter = ConcurrencyLimiter::new(&config.scan);
+    if limiter.is_adaptive() {
+        eprintln!(
+            "⚙️ Adaptive concurrency enabled: {}..{} in-flight checks",
+            limiter.limit(), config.scan.max_concurrent
+        );
+    }
 
     // Store active proxies and proxy data for batch writing
-    let active_proxies = Arc::new(Mutex::new(Vec::new()));
-    let proxy_data_batch = Arc::new(Mutex::new(Vec::<ProxyData>::new()));
-    let batch_counter = Arc::new(Mutex::new(0usize));
-    // Batch size for incremental PostgreSQL writes
-    // Note: Used as literal value (50) in line 832 condition check
-
-    // Initialize PostgreSQL connection pool (REQUIRED)
-    println!("🔌 Initializing PostgreSQL connection...");
-    let pg_pool = match create_pg_pool() {
-        Ok(pool) => pool,
-        Err(e) => {
-            eprintln!("❌ Failed to create PostgreSQL connection pool: {}", e);
-            eprintln!("💡 Please configure DATABASE_URL and ensure database is accessible");
-            std::process::exit(1);
-        }
-    };
+    let ctx = Arc::new(ScanContext {
+        checker,
+        sinks: Mutex::new(sinks),
+        log_live: config.output.stdout.is_none(),
+        rejected,
+        stats: Mutex::new(ScanStats::default()),
+        progress: Progress::new(Arc::clone(&limiter)),
+        limiter,
+        hosts: HostLimiter::new(&config.scan),
+        proxy_data_batch: Mutex::new(Vec::new()),
+        batch_counter: Mutex::new(0),
+        pg_pool,
+        // Generate batch timestamp for this run
+        batch_time: chrono::Utc::now(),
+    });
 
-    // Test database connection (REQUIRED)
-    if let Err(e) = test_database_connection(&pg_pool).await {
-        eprintln!("❌ Database connection test failed: {}", e);
-        eprintln!("💡 Please check: DATABASE_URL, network connectivity, and run schema.sql");
-        std::process::exit(1);
+    // channel 只缓冲少量待检测地址，内存占用与输入大小无关
+    let (input_rx, reader) = spawn_reader(
+        sources,
+        config.scan.ports.clone(),
+        config.scan.dedup,
+        exclusions,
+        config.scan.max_concurrent * 2,
+        validated.is_none().then(|| Arc::clone(&ctx.progress)),
+    );
+    if let Some(summary) = &validated {
+        ctx.progress.set_total(summary.total_inputs());
     }
-    println!("✅ Database ready for sync");
-
-    // Generate batch timestamp for this run
-    let batch_time = chrono::Utc::now();
+    // 进入 buffer_unordered 之前先取得 IP/网段名额；名额已满的地址暂存在 HostQueue，先检测其他主机，
+    // 否则按地址排序或由网段、端口集合展开的输入会让等待同一主机的任务占满并发
+    // 暂存数达到上限后暂停读取，只等名额释放
+    let max_waiting = config.scan.max_concurrent * 64;
+    let hosts = &ctx.hosts;
+    let inputs = futures::stream::unfold(
+        (input_rx, HostQueue::new(), false, false),
+        move |(mut rx, mut waiting, mut input_done, mut retry)| async move {
+            loop {
+                // 只在有名额释放后重试暂存的地址
+                if retry {
+                    match waiting.pop_ready(hosts) {
+                        Some((target, slot)) => {
+                            let item = (InputItem::Target(target), Some(slot));
+                            return Some((item, (rx, waiting, input_done, retry)));
+                        }
+                        None => retry = false,
+                    }
+                }
+                if input_done && waiting.is_empty() {
+                    return None;
+                }
+                let can_read = !input_done && waiting.len() < max_waiting;
+                tokio::select! {
+                    item = rx.recv(), if can_read => match item {
+                        Some(InputItem::Target(target)) => match hosts.try_acquire(target.ip) {
+                            Ok(slot) => {
+                                let item = (InputItem::Target(target), Some(slot));
+                                return Some((item, (rx, waiting, input_done, retry)));
+                            }
+                            Err(_) => waiting.push(target.ip, target),
+                        },
+                        Some(item) => return Some(((item, None), (rx, waiting, input_done, retry))),
+                        None => input_done = true,
+                    },
+                    () = hosts.released() => retry = true,
+                }
+            }
+        },
+    );
 
-    // Share pool across tasks
-    let pg_pool_shared = Arc::new(pg_pool);
+    let progress_task = config.scan.progress.then(|| ctx.progress.spawn());
 
     // Process proxies concurrently
-    let tasks = futures::stream::iter(
-        proxies.into_iter().map(|proxy_line| {
-            let original_ip = original_ip.clone();
-            let active_proxies = Arc::clone(&active_proxies);
-            let proxy_data_batch = Arc::clone(&proxy_data_batch);
-            let batch_counter = Arc::clone(&batch_counter);
-            let pg_pool_clone = Arc::clone(&pg_pool_shared);
-            let country_reader = Arc::clone(&country_reader);
-            let city_reader = city_reader.clone();
-            let asn_reader = asn_reader.clone();
-            let anonymous_reader = anonymous_reader.clone();
-            let abuse_ips = Arc::clone(&abuse_ips);
-            let firehol_cidrs = Arc::clone(&firehol_cidrs);
-
-            // tokio::spawn akan menjalankan setiap future process_proxy secara independen
-            // Ini adalah cara yang lebih idiomatik untuk menjalankan banyak tugas async di Tokio
-            // daripada hanya mengandalkan buffer_unordered pada stream dari async blok.
-            // Namun, karena buffer_unordered sudah menangani konkurensi,
-            // tokio::spawn di sini mungkin redundan jika process_proxy itu sendiri tidak
-            // melakukan spawn lebih lanjut atau operasi berat CPU yang panjang.
-            // Untuk I/O bound seperti ini, buffer_unordered sudah cukup.
-            // Mari kita tetap dengan struktur asli untuk kesederhanaan, karena buffer_unordered sudah menangani konkurensi.
-            async move {
-                process_proxy(
-                    proxy_line,
-                    &original_ip,
-                    &active_proxies,
-                    &proxy_data_batch,
-                    &batch_counter,
-                    &pg_pool_clone,
-                    batch_time,
-                    &country_reader,
-                    city_reader.as_deref(),
-                    asn_reader.as_deref(),
-                    anonymous_reader.as_deref(),
-                    &abuse_ips,
-                    &firehol_cidrs,
-                ).await;
+    let tasks = inputs.map(|(item, host_slot)| {
+        let ctx = Arc::clone(&ctx);
+        async move {
+            match item {
+                InputItem::Target(target) => process_proxy(target, host_slot, &ctx).await,
+                // 格式错误的行不参与检测，但计入统计与 rejected 输出
+                InputItem::Invalid(error) => {
+                    let detail = format!("{}:{}: {} ({})", error.source, error.line, error.reason, error.content.trim());
+                    ctx.reject("", None, &CheckError::InvalidInput(detail));
+                }
             }
-        })
-    ).buffer_unordered(MAX_CONCURRENT).collect::<Vec<()>>();
+            ctx.progress.inc();
+        }
+    }).buffer_unordered(config.scan.max_concurrent).for_each(|()| async {});
 
     tasks.await;
 
-    // Write final batch if any remaining proxies
-    {
-        let batch = proxy_data_batch.lock().unwrap();
-        if !batch.is_empty() {
-            println!("📤 Writing final batch of {} proxies to PostgreSQL...", batch.len());
-            match batch_insert_proxies(&pg_pool_shared, &batch, batch_time).await {
-                Ok(_) => println!("✅ Final batch written successfully"),
-                Err(e) => eprintln!("❌ Failed to write final batch: {}", e),
-            }
-        }
-    }
-
-    // Clean up old records
-    match cleanup_old_proxies(&pg_pool_shared, batch_time).await {
-        Ok(_) => println!("✅ Database cleanup completed"),
-        Err(e) => eprintln!("❌ Failed to cleanup old proxies: {}", e),
+    if let Some(handle) = progress_task {
+        ctx.progress.finish(handle);
     }
 
-    // Save active proxies to file
-    let active_proxies_locked = active_proxies.lock().unwrap();
-    if !active_proxies_locked.is_empty() {
-        let mut file = File::create(OUTPUT_FILE)?;
-        for proxy_csv in active_proxies_locked.iter() {
-            writeln!(file, "{}", proxy_csv)?;
+    // 读取中途出错时已检测的结果仍照常写出，最后再返回错误
+    let input = reader.await?;
+    if streamed {
+        if let Ok(summary) = &input {
+            summary.print_report();
         }
-        println!("✅ All active proxies saved to {}", OUTPUT_FILE);
-    } else {
-        println!("No active proxies found");
     }
 
-    println!("Proxy checking completed.");
-    Ok(())
-}
-
-fn read_proxy_file(file_path: &str) -> io::Result<Vec<String>> {
-    let file = File::open(file_path)?;
-    let reader = BufReader::new(file);
-    let mut proxies = Vec::new();
+    if let Some(pg_pool) = &ctx.pg_pool {
+        // Write final batch if any remaining proxies
+        let batch = std::mem::take(&mut *ctx.proxy_data_batch.lock().unwrap());
+        if !batch.is_empty() {
+            eprintln!("📤 Writing final batch of {} proxies to PostgreSQL...", batch.len());
+            match batch_insert_proxies(pg_pool, &batch, ctx.batch_time).await {
+                Ok(_) => eprintln!("✅ Final batch written successfully"),
+                Err(e) => eprintln!("❌ Failed to write final batch: {}", e),
+            }
+        }
 
-    for line in reader.lines() {
-        let line = line?;
-        if !line.trim().is_empty() {
-            proxies.push(line);
+        // Clean up old records
+        match cleanup_old_proxies(pg_pool, ctx.batch_time).await {
+            Ok(_) => eprintln!("✅ Database cleanup completed"),
+            Err(e) => eprintln!("❌ Failed to cleanup old proxies: {}", e),
         }
     }
 
-    Ok(proxies)
-}
+    // Flush all output sinks
+    for sink in ctx.sinks.lock().unwrap().iter_mut() {
+        sink.finish()?;
+    }
+    if let Some(rejected) = &ctx.rejected {
+        rejected.lock().unwrap().finish()?;
+    }
+    eprintln!("✅ All active proxies saved");
 
-// 读取 AbuseIPDB 黑名单 IP 列表
-fn load_abuse_ips(file_path: &str) -> HashSet<IpAddr> {
-    let mut abuse_ips = HashSet::new();
-
-    match File::open(file_path) {
-        Ok(file) => {
-            let reader = BufReader::new(file);
-            for line in reader.lines() {
-                if let Ok(line) = line {
-                    // 格式: ip,country_code,abuse_confidence_score
-                    let parts: Vec<&str> = line.split(',').collect();
-                    if !parts.is_empty() {
-                        if let Ok(ip) = parts[0].trim().parse::<IpAddr>() {
-                            abuse_ips.insert(ip);
-                        }
-                    }
-                }
-            }
-            println!("Loaded {} abuse IPs from {}", abuse_ips.len(), file_path);
-        }
+    let summary = match input {
+        Ok(summary) => summary,
         Err(e) => {
-            eprintln!("Warning: Could not load abuse IP list ({}): {}. Abuse IP filtering will be disabled.", file_path, e);
+            eprintln!("Error reading proxy file: {}", e);
+            return Err(e.into());
         }
-    }
-
-    abuse_ips
-}
+    };
 
-// 读取 FireHOL CIDR 网段列表
-fn load_firehol_cidrs(file_path: &str) -> Vec<IpNetwork> {
-    let mut cidrs = Vec::new();
-
-    match File::open(file_path) {
-        Ok(file) => {
-            let reader = BufReader::new(file);
-            for line in reader.lines() {
-                if let Ok(line) = line {
-                    let line = line.trim();
-                    if !line.is_empty() {
-                        if let Ok(network) = line.parse::<IpNetwork>() {
-                            cidrs.push(network);
-                        }
-                    }
-                }
-            }
-            println!("Loaded {} CIDR ranges from {}", cidrs.len(), file_path);
-        }
-        Err(e) => {
-            eprintln!("Warning: Could not load FireHOL CIDR list ({}): {}. CIDR filtering will be disabled.", file_path, e);
+    let stats = std::mem::take(&mut *ctx.stats.lock().unwrap());
+    let report = stats.into_report(&summary, ctx.batch_time);
+    report.print_table();
+    if let Some(path) = &config.output.report {
+        match report.write_json(path) {
+            Ok(_) => eprintln!("📝 Scan report written to {}", path),
+            Err(e) => eprintln!("❌ Failed to write scan report {}: {}", path, e),
         }
     }
 
-    cidrs
-}
-
-// 检查 IP 是否在 CIDR 网段内
-fn is_ip_in_cidr_list(ip: IpAddr, cidrs: &[IpNetwork]) -> bool {
-    cidrs.iter().any(|network| network.contains(ip))
+    eprintln!("Proxy checking completed.");
+    Ok(())
 }
 
-// 初始化 PostgreSQL 连接池（必需）
-fn create_pg_pool() -> Result<Pool> {
-    let database_url = match env::var("DATABASE_URL") {
-        Ok(url) => {
-            if url.is_empty() {
-                return Err("DATABASE_URL is empty. PostgreSQL connection is required.".into());
-            }
-            println!("✅ DATABASE_URL configured: {}...", &url.chars().take(20).collect::<String>());
-            url
-        }
-        Err(_) => {
-            return Err("DATABASE_URL not set. PostgreSQL connection is required.".into());
-        }
+// 检测单个代理并打印结果
+async fn run_check(args: CheckArgs, config: Config) -> Result<()> {
+    let target = match InputParser::new(&[], Arc::from("")).parse_line(&args.proxy) {
+        Some(Ok(targets)) if targets.iter().nth(1).is_none() => targets.iter().next().unwrap(),
+        Some(Err(reason)) => return Err(format!("Invalid proxy '{}': {}", args.proxy, reason).into()),
+        _ => return Err(format!("Invalid proxy '{}'. Expected ip:port, [ipv6]:port or ip,port", args.proxy).into()),
     };
+    let (ip, port) = (target.ip.to_string(), target.port);
 
-    let mut cfg = Config::new();
-    cfg.url = Some(database_url);
-    cfg.manager = Some(ManagerConfig {
-        recycling_method: RecyclingMethod::Fast,
-    });
+    let geo = GeoDatabases::load(&config.data, &config.filters)?;
+    let checker = Checker::new(&config.probe, &config.tls, &config.retry, geo).await?;
 
-    match cfg.create_pool(Some(Runtime::Tokio1), NoTls) {
-        Ok(pool) => {
-            println!("✅ PostgreSQL connection pool created successfully");
-            Ok(pool)
+    match checker.check(&ip, port).await {
+        Ok((proxy_data, _meta)) => {
+            println!("CF PROXY LIVE ✅: {}", csv_line(&proxy_data.csv_fields()));
+            Ok(())
+        }
+        Err(e @ CheckError::Filtered { .. }) => {
+            println!("CF PROXY FILTERED 🚫 ({}): {}:{}", e, ip, port);
+            std::process::exit(1);
         }
         Err(e) => {
-            Err(format!("Failed to create PostgreSQL connection pool: {}", e).into())
+            println!("CF PROXY DEAD ❌ ({}): {}:{}", e, ip, port);
+            std::process::exit(1);
         }
     }
 }
 
-// 测试数据库连接并验证表结构
-async fn test_database_connection(pool: &Pool) -> Result<()> {
-    println!("🔍 Testing database connection...");
-
-    let client = pool.get().await.map_err(|e| {
-        eprintln!("❌ Failed to get database client: {}", e);
-        e
-    })?;
-
-    println!("✅ Database connection successful");
-
-    // Check if proxies table exists
-    let table_check = client.query(
-        "SELECT EXISTS (
-            SELECT FROM information_schema.tables
-            WHERE table_name = 'proxies'
-        )",
-        &[]
-    ).await?;
-
-    if let Some(row) = table_check.first() {
-        let exists: bool = row.get(0);
-        if exists {
-            println!("✅ Table 'proxies' exists");
-
-            // Get row count
-            let count_result = client.query("SELECT COUNT(*) FROM proxies", &[]).await?;
-            if let Some(row) = count_result.first() {
-                let count: i64 = row.get(0);
-                println!("📊 Current proxy count in database: {}", count);
+// 仅查询 GeoIP/ASN 与黑名单信息，不发起任何连接
+fn run_enrich(args: EnrichArgs, config: Config) -> Result<()> {
+    let mut ips = args.ips;
+    if let Some(input) = &args.input {
+        // 同一 IP 的多个端口只查询一次；Vec 保持输入顺序，HashSet 用于判重
+        let mut seen: HashSet<String> = ips.iter().cloned().collect();
+        let summary = read_inputs(open_inputs(std::slice::from_ref(input))?, &config.scan.ports, true, &Exclusions::default(), |item| {
+            if let InputItem::Target(target) = item {
+                let ip = target.ip.to_string();
+                if seen.insert(ip.clone()) {
+                    ips.push(ip);
+                }
             }
-        } else {
-            eprintln!("❌ Table 'proxies' does not exist! Please run schema.sql first.");
-            return Err("Table 'proxies' not found".into());
-        }
+            true
+        })?;
+        summary.print_report();
     }
 
-    Ok(())
-}
-
-// 批量写入代理数据到 PostgreSQL
-async fn batch_insert_proxies(pool: &Pool, proxies: &[ProxyData], batch_time: chrono::DateTime<chrono::Utc>) -> Result<()> {
-    if proxies.is_empty() {
-        return Ok(());
+    if ips.is_empty() {
+        return Err("No IPs given. Pass IPs as arguments or use --input".into());
     }
 
-    let mut client = pool.get().await?;
-
-    // 开始事务
-    let transaction = client.transaction().await?;
-
-    // 批量插入（使用 UPSERT 策略）
-    let stmt = transaction.prepare(
-        "INSERT INTO proxies (ip, port, country_code, country_name, city_code, city_name, asn_number, org_name, updated_at)
-         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
-         ON CONFLICT (ip, port)
-         DO UPDATE SET
-            country_code = EXCLUDED.country_code,
-            country_name = EXCLUDED.country_name,
-            city_code = EXCLUDED.city_code,
-            city_name = EXCLUDED.city_name,
-            asn_number = EXCLUDED.asn_number,
-            org_name = EXCLUDED.org_name,
-            updated_at = EXCLUDED.updated_at"
-    ).await?;
-
-    let mut inserted = 0;
-    for proxy in proxies {
-        transaction.execute(
-            &stmt,
-            &[
-                &proxy.ip,
-                &(proxy.port as i32),
-                &proxy.country_code,
-                &proxy.country_name,
-                &proxy.city_code,
-                &proxy.city_name,
-                &proxy.asn_number,
-                &proxy.org_name,
-                &batch_time,
-            ],
-        ).await?;
-        inserted += 1;
+    let geo = GeoDatabases::load(&config.data, &config.filters)?;
+
+    // CSV 格式: ip,国家代码,国家名,城市代码,城市名,ASN编号,组织名,过滤原因
+    for ip in ips {
+        let info = geo.lookup(&ip);
+        let filter_reason = geo.filter_reason(&ip).map(|kind| kind.to_string()).unwrap_or_default();
+        println!("{}", csv_line(&[
+            ip,
+            info.country_code, info.country_name,
+            info.city_code, info.city_name,
+            info.asn_number, info.org_name,
+            filter_reason,
+        ]));
     }
 
-    // 提交事务
-    transaction.commit().await?;
-
-    println!("✅ Inserted/Updated {} proxies to PostgreSQL", inserted);
-    Ok(())
-}
-
-// 清理旧数据（保留本次更新的数据）
-async fn cleanup_old_proxies(pool: &Pool, batch_time: chrono::DateTime<chrono::Utc>) -> Result<()> {
-    let client = pool.get().await?;
-
-    let rows_deleted = client.execute(
-        "DELETE FROM proxies WHERE updated_at < $1",
-        &[&batch_time],
-    ).await?;
-
-    println!("✅ Cleaned up {} old proxy records from PostgreSQL", rows_deleted);
     Ok(())
 }
 
-async fn check_connection(
-    host: &str,
-    path: &str,
-    proxy: Option<(&str, u16)>,
-) -> Result<Value> {
-    let timeout_duration = Duration::from_secs(TIMEOUT_SECONDS);
-
-    // Bungkus seluruh operasi koneksi dalam tokio::time::timeout
-    match tokio::time::timeout(timeout_duration, async {
-        // Build HTTP request payload
-        let payload = format!(
-            "GET {} HTTP/1.1\r\n\
-             Host: {}\r\n\
-             User-Agent: Mozilla/5.0 (Windows NT 10.0) AppleWebKit/537.36 \
-             (KHTML, like Gecko) Chrome/42.0.2311.135 Safari/537.36 Edge/12.10240\r\n\
-             Connection: close\r\n\r\n",
-            path, host
-        );
-
-        // Create TCP connection
-        let stream = if let Some((proxy_ip, proxy_port)) = proxy {
-            // *** PERUBAHAN UTAMA DI SINI ***
-            // Menangani alamat IPv6 dengan benar dengan membungkusnya dalam kurung siku.
-            let connect_addr = if proxy_ip.contains(':') {
-                // Ini adalah alamat IPv6, formatnya menjadi "[ipv6]:port"
-                format!("[{}]:{}", proxy_ip, proxy_port)
-            } else {
-                // Ini adalah alamat IPv4, formatnya tetap "ipv4:port"
-                format!("{}:{}", proxy_ip, proxy_port)
-            };
-            TcpStream::connect(connect_addr).await?
-        } else {
-            // Connect directly to host (Tokio's connect can resolve hostnames)
-            TcpStream::connect(format!("{}:443", host)).await?
-        };
-
-        // Create TLS connection
-        // NativeTlsConnector dikonfigurasi terlebih dahulu
-        let native_connector = NativeTlsConnector::builder().build()?;
-        // Kemudian dibungkus dengan TokioTlsConnector untuk penggunaan async
-        let tokio_connector = TokioTlsConnector::from(native_connector);
-
-        let mut tls_stream = tokio_connector.connect(host, stream).await?;
-
-        // Send HTTP request
-        tls_stream.write_all(payload.as_bytes()).await?;
-
-        // Read response
-        let mut response = Vec::new();
-        // Menggunakan buffer yang sama ukurannya
-        let mut buffer = [0; 4096];
-
-        // Loop untuk membaca data dari stream
-        // AsyncReadExt::read akan mengembalikan Ok(0) saat EOF.
-        loop {
-            match tls_stream.read(&mut buffer).await {
-                Ok(0) => break, // End of stream
-                Ok(n) => response.extend_from_slice(&buffer[..n]),
-                Err(e) => {
-                    // Jika jenis error adalah WouldBlock, dalam konteks async,
-                    // ini biasanya ditangani oleh runtime (tidak akan sampai ke sini jika .await digunakan dengan benar).
-                    // Namun, jika ada error I/O lain, kita return.
-                    return Err(Box::new(e) as Box<dyn std::error::Error + Send + Sync>);
-                }
-            }
-        }
-
-        // Parse response
-        let response_str = String::from_utf8_lossy(&response);
-
-        // Split headers and body
-        if let Some(body_start) = response_str.find("\r\n\r\n") {
-            let body = &response_str[body_start + 4..];
+async fn run_db(args: DbArgs, config: Config) -> Result<()> {
+    let pool = create_pg_pool(config.database.url.as_deref())?;
+    test_database_connection(&pool).await?;
 
-            // Try to parse the JSON body
-            match serde_json::from_str::<Value>(body.trim()) {
-                Ok(json_data) => Ok(json_data),
-                Err(e) => {
-                    eprintln!("Failed to parse JSON: {}", e);
-                    eprintln!("Response body for {}:{}: {}", host, proxy.map_or_else(|| "direct".to_string(), |(ip,p)| format!("{}:{}",ip,p)), body);
-                    Err("Invalid JSON response".into())
-                }
-            }
-        } else {
-            Err("Invalid HTTP response: No separator found".into())
+    match args.command {
+        DbCommand::Status => Ok(()),
+        DbCommand::Migrate => migrate_schema(&pool).await,
+        DbCommand::Cleanup { older_than_hours } => {
+            let cutoff = chrono::Utc::now() - chrono::Duration::hours(older_than_hours);
+            cleanup_old_proxies(&pool, cutoff).await
         }
-    }).await {
-        Ok(inner_result) => inner_result, // Hasil dari blok async (bisa Ok atau Err)
-        Err(_) => Err(Box::new(io::Error::new(io::ErrorKind::TimedOut, "Connection attempt timed out")) as Box<dyn std::error::Error + Send + Sync>), // Error karena timeout
     }
 }
 
-
 #[allow(dead_code)]
 fn clean_org_name(org_name: &str) -> String {
     org_name.chars()
@@ -560,285 +491,92 @@ fn clean_org_name(org_name: &str) -> String {
         .collect()
 }
 
-// 查询 IP 地理位置信息
-// 返回: (国家代码, 国家名, 城市代码, 城市名)
-fn get_geo_info(
-    country_reader: &Reader<Vec<u8>>,
-    city_reader: Option<&Reader<Vec<u8>>>,
-    ip_str: &str,
-) -> (String, String, String, String) {
-    let ip: IpAddr = match ip_str.parse() {
-        Ok(ip) => ip,
-        Err(_) => return (String::new(), String::new(), String::new(), String::new()),
-    };
-
-    // 查询国家信息
-    let (country_code, country_name) = match country_reader.lookup::<geoip2::Country>(ip) {
-        Ok(country_data) => {
-            let code = country_data
-                .country
-                .as_ref()
-                .and_then(|c| c.iso_code)
-                .unwrap_or("")
-                .to_string();
-
-            let name = country_data
-                .country
-                .as_ref()
-                .and_then(|c| c.names.as_ref())
-                .and_then(|names| {
-                    names.get("zh-CN")
-                        .or_else(|| names.get("en"))
-                        .map(|s| s.to_string())
-                })
-                .unwrap_or_default();
-
-            (code, name)
-        }
-        Err(_) => (String::new(), String::new()),
-    };
-
-    // 查询城市信息（如果有城市数据库）
-    let (city_code, city_name) = if let Some(reader) = city_reader {
-        match reader.lookup::<geoip2::City>(ip) {
-            Ok(city_data) => {
-                // GeoLite2 没有城市代码，使用城市名的英文作为代码
-                let code = city_data
-                    .city
-                    .as_ref()
-                    .and_then(|c| c.names.as_ref())
-                    .and_then(|names| names.get("en").map(|s| s.to_string()))
-                    .unwrap_or_default();
-
-                let name = city_data
-                    .city
-                    .as_ref()
-                    .and_then(|c| c.names.as_ref())
-                    .and_then(|names| {
-                        names.get("zh-CN")
-                            .or_else(|| names.get("en"))
-                            .map(|s| s.to_string())
-                    })
-                    .unwrap_or_default();
-
-                (code, name)
+impl ScanContext {
+    // 记录失败原因，并在启用 rejected 输出时写入
+    fn reject(&self, ip: &str, port: Option<u16>, error: &CheckError) {
+        self.stats.lock().unwrap().record_failure(error);
+        if let Some(rejected) = &self.rejected {
+            if let Err(e) = rejected.lock().unwrap().write(ip, port, error) {
+                eprintln!("❌ Failed to write rejected entry: {}", e);
             }
-            Err(_) => (String::new(), String::new()),
         }
-    } else {
-        (String::new(), String::new())
-    };
-
-    (country_code, country_name, city_code, city_name)
+    }
 }
 
-// 查询 ASN 信息
-// 返回: (ASN 编号, 组织名)
-fn get_asn_info(
-    asn_reader: &Reader<Vec<u8>>,
-    ip_str: &str,
-) -> (String, String) {
-    let ip: IpAddr = match ip_str.parse() {
-        Ok(ip) => ip,
-        Err(_) => return (String::new(), String::new()),
-    };
-
-    match asn_reader.lookup::<geoip2::Asn>(ip) {
-        Ok(asn_data) => {
-            let asn_number = asn_data
-                .autonomous_system_number
-                .map(|n| n.to_string())
-                .unwrap_or_default();
+// host_slot 为调度时取得的 IP/网段名额，检测结束时释放
+async fn process_proxy(target: ProxyTarget, host_slot: Option<HostSlot<'_>>, ctx: &ScanContext) {
+    let ip = target.ip.to_string();
+    let port_num = target.port;
+    let _host_slot = host_slot;
 
-            let org_name = asn_data
-                .autonomous_system_organization
-                .unwrap_or("")
-                .to_string();
+    // buffer_unordered 限制 task 数量，limiter 决定实际同时进行的检测数
+    let _permit = ctx.limiter.acquire().await;
 
-            (asn_number, org_name)
+    // 从端口集合展开的地址大多没有开放，先用一次 TCP 连接筛掉
+    if target.from_port_set {
+        if let Err(e) = ctx.checker.precheck(target.ip, port_num).await {
+            ctx.limiter.record(Some(&e));
+            ctx.reject(&ip, Some(port_num), &e);
+            return;
         }
-        Err(_) => (String::new(), String::new()),
     }
-}
-
-// 检查 IP 是否为匿名代理（VPN/公共代理/Tor）
-fn is_anonymous_ip(
-    anonymous_reader: &Reader<Vec<u8>>,
-    ip_str: &str,
-) -> (bool, String) {
-    let ip: IpAddr = match ip_str.parse() {
-        Ok(ip) => ip,
-        Err(_) => return (false, "无法解析IP".to_string()),
-    };
 
-    match anonymous_reader.lookup::<geoip2::AnonymousIp>(ip) {
-        Ok(anonymous_data) => {
-            let is_vpn = anonymous_data.is_anonymous_vpn.unwrap_or(false);
-            let is_proxy = anonymous_data.is_public_proxy.unwrap_or(false);
-            let is_tor = anonymous_data.is_tor_exit_node.unwrap_or(false);
-
-            if is_vpn || is_proxy || is_tor {
-                let mut reasons = Vec::new();
-                if is_vpn { reasons.push("VPN"); }
-                if is_proxy { reasons.push("公共代理"); }
-                if is_tor { reasons.push("Tor出口节点"); }
-                (true, reasons.join("+"))
-            } else {
-                (false, "正常IP".to_string())
-            }
-        }
-        Err(_) => {
-            // 数据库中没有记录，视为正常IP（不在匿名IP列表中）
-            (false, "未知(默认允许)".to_string())
+    let (mut proxy_data, meta) = match ctx.checker.check(&ip, port_num).await {
+        Ok(result) => result,
+        Err(e) => {
+            ctx.limiter.record(Some(&e));
+            ctx.reject(&ip, Some(port_num), &e);
+            return;
         }
+    };
+    ctx.limiter.record(None);
+    proxy_data.source = target.source.to_string();
+    ctx.stats.lock().unwrap().record_live(&proxy_data);
+    ctx.progress.record_live();
+
+    if ctx.log_live {
+        ctx.progress.clear();
+        eprintln!("CF PROXY LIVE ✅: {}", csv_line(&proxy_data.csv_fields()));
     }
-}
 
-async fn process_proxy(
-    proxy_line: String,
-    original_ip: &str,
-    active_proxies: &Arc<Mutex<Vec<String>>>,
-    proxy_data_batch: &Arc<Mutex<Vec<ProxyData>>>,
-    batch_counter: &Arc<Mutex<usize>>,
-    pg_pool: &Arc<Pool>,
-    batch_time: chrono::DateTime<chrono::Utc>,
-    country_reader: &Reader<Vec<u8>>,
-    city_reader: Option<&Reader<Vec<u8>>>,
-    asn_reader: Option<&Reader<Vec<u8>>>,
-    anonymous_reader: Option<&Reader<Vec<u8>>>,
-    abuse_ips: &HashSet<IpAddr>,
-    firehol_cidrs: &[IpNetwork],
-) {
-    let parts: Vec<&str> = proxy_line.split(',').collect();
-    if parts.len() < 4 {
-        println!("Invalid proxy line format: {}. Expected ip,port,country,org", proxy_line);
-        return;
+    // Stream the result to every output sink
+    for sink in ctx.sinks.lock().unwrap().iter_mut() {
+        if let Err(e) = sink.write(&proxy_data, &meta) {
+            eprintln!("❌ Failed to write result: {}", e);
+        }
     }
 
-    let ip = parts[0];
-    let port_str = parts[1]; // Renamed to avoid conflict with port_num
-    let _country = parts[2]; // 保留以备将来使用
-    let _org = parts[3]; // 保留以备将来使用
-
-    let port_num = match port_str.parse::<u16>() {
-        Ok(p) => p,
-        Err(_) => {
-            println!("Invalid port number: {} in line: {}", port_str, proxy_line);
-            return;
-        }
+    // Add to batch for PostgreSQL
+    let pg_pool = match &ctx.pg_pool {
+        Some(pool) => pool,
+        None => return,
     };
 
-    match check_connection(IP_RESOLVER, PATH_RESOLVER, Some((ip, port_num))).await {
-        Ok(proxy_data) => {
-            if let Some(Value::String(proxy_ip)) = proxy_data.get("clientIp") {
-                if proxy_ip != original_ip {
-                    // 解析 IP 地址用于过滤检查
-                    let ip_addr = match ip.parse::<IpAddr>() {
-                        Ok(addr) => addr,
-                        Err(_) => {
-                            //println!("CF PROXY FILTERED 🚫 (Invalid IP format): {}:{}", ip, port_num);
-                            return;
-                        }
-                    };
+    let mut batch = ctx.proxy_data_batch.lock().unwrap();
+    batch.push(proxy_data);
 
-                    // 检查是否为匿名IP（VPN/公共代理/Tor）- 仅当数据库可用时
-                    if let Some(anon_reader) = anonymous_reader {
-                        let (is_anonymous, _reason) = is_anonymous_ip(anon_reader, ip);
+    let mut counter = ctx.batch_counter.lock().unwrap();
+    *counter += 1;
 
-                        if is_anonymous {
-                            //println!("CF PROXY FILTERED 🚫 (匿名IP: {}): {}:{}", reason, ip, port_num);
-                            return;
-                        }
-                    }
+    // Trigger batch write when reaching BATCH_SIZE (50)
+    if *counter >= 50 {
+        eprintln!("📤 Writing batch of {} proxies to PostgreSQL...", batch.len());
 
-                    // 检查是否在 AbuseIPDB 黑名单中
-                    if !abuse_ips.is_empty() && abuse_ips.contains(&ip_addr) {
-                        //println!("CF PROXY FILTERED 🚫 (AbuseIPDB 黑名单): {}:{}", ip, port_num);
-                        return;
-                    }
+        // Take data for async write; this also clears the batch
+        let batch_to_write = std::mem::take(&mut *batch);
+        let pool_clone = Arc::clone(pg_pool);
+        let batch_time = ctx.batch_time;
 
-                    // 检查是否在 FireHOL CIDR 黑名单中
-                    if !firehol_cidrs.is_empty() && is_ip_in_cidr_list(ip_addr, firehol_cidrs) {
-                       // println!("CF PROXY FILTERED 🚫 (FireHOL CIDR 黑名单): {}:{}", ip, port_num);
-                        return;
-                    }
+        // Reset counter
+        *counter = 0;
 
-                    // 获取地理位置信息
-                    let (country_code, country_name, city_code, city_name) =
-                        get_geo_info(country_reader, city_reader, ip);
-
-                    // 获取 ASN 信息
-                    let (asn_number, org_name) = if let Some(reader) = asn_reader {
-                        get_asn_info(reader, ip)
-                    } else {
-                        (String::new(), String::new())
-                    };
-
-                    // CSV 格式: ip,port,国家代码,国家名,城市代码,城市名,ASN编号,组织名
-                    let proxy_entry = format!("{},{},{},{},{},{},{},{}",
-                        ip, port_num,
-                        country_code, country_name,
-                        city_code, city_name,
-                        asn_number, org_name
-                    );
-                    println!("CF PROXY LIVE ✅: {}", proxy_entry);
-
-                    // Add to active proxies for file output
-                    {
-                        let mut active_proxies_locked = active_proxies.lock().unwrap();
-                        active_proxies_locked.push(proxy_entry);
-                    }
-
-                    // Add to batch for PostgreSQL
-                    let proxy_data = ProxyData {
-                        ip: ip.to_string(),
-                        port: port_num,
-                        country_code: country_code.clone(),
-                        country_name: country_name.clone(),
-                        city_code: city_code.clone(),
-                        city_name: city_name.clone(),
-                        asn_number: asn_number.clone(),
-                        org_name: org_name.clone(),
-                    };
-
-                    {
-                        let mut batch = proxy_data_batch.lock().unwrap();
-                        batch.push(proxy_data);
-
-                        let mut counter = batch_counter.lock().unwrap();
-                        *counter += 1;
-
-                        // Trigger batch write when reaching BATCH_SIZE (50)
-                        if *counter >= 50 {
-                            println!("📤 Writing batch of {} proxies to PostgreSQL...", batch.len());
-
-                            // Clone data for async write
-                            let batch_to_write = batch.clone();
-                            let pool_clone = Arc::clone(pg_pool);
-
-                            // Clear batch and reset counter
-                            batch.clear();
-                            *counter = 0;
-
-                            // Spawn async task to write batch
-                            tokio::spawn(async move {
-                                if let Err(e) = batch_insert_proxies(&pool_clone, &batch_to_write, batch_time).await {
-                                    eprintln!("❌ Failed to write batch to PostgreSQL: {}", e);
-                                } else {
-                                    println!("✅ Batch write completed successfully");
-                                }
-                            });
-                        }
-                    }
-                } else {
-                   // println!("CF PROXY DEAD ❌ (Same IP as original): {}:{}", ip, port_num);
-                }
+        // Spawn async task to write batch
+        tokio::spawn(async move {
+            if let Err(e) = batch_insert_proxies(&pool_clone, &batch_to_write, batch_time).await {
+                eprintln!("❌ Failed to write batch to PostgreSQL: {}", e);
             } else {
-               // println!("CF PROXY DEAD ❌ (No clientIp field in response): {}:{} - Response: {:?}", ip, port_num, proxy_data);
+                eprintln!("✅ Batch write completed successfully");
             }
-        },
-        Err(_e) => {
-           // println!("CF PROXY DEAD ⏱️ (Error connecting): {}:{} - {}", ip, port_num, _e);
-        }
+        });
     }
 }
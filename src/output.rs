@@ -0,0 +1,302 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::OutputConfig;
+use crate::error::CheckError;
+use crate::{CheckMeta, ProxyData};
+
+// CSV 列顺序，与 ProxyData::csv_fields 保持一致
+const CSV_HEADER: &[&str] = &[
+    "ip",
+    "port",
+    "country_code",
+    "country_name",
+    "city_code",
+    "city_name",
+    "asn_number",
+    "org_name",
+    "connect_ms",
+    "tls_handshake_ms",
+    "ttfb_ms",
+    "total_ms",
+    "cf_client_ip",
+    "cf_colo",
+    "cf_asn",
+    "cf_as_organization",
+    "cf_country",
+    "cf_city",
+    "cf_region",
+    "cf_http_protocol",
+    "cf_tls_version",
+    "exit_differs",
+    "exit_country_code",
+    "exit_country_name",
+    "exit_city_code",
+    "exit_city_name",
+    "exit_asn_number",
+    "exit_org_name",
+    "entry_blocklist",
+    "exit_blocklist",
+    "attempts",
+    "source",
+];
+
+// 输出格式（用于 stdout 流式输出）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Csv,
+    Tsv,
+    Jsonl,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(OutputFormat::Csv),
+            "tsv" => Ok(OutputFormat::Tsv),
+            "jsonl" | "ndjson" => Ok(OutputFormat::Jsonl),
+            _ => Err(format!("unknown output format '{}', expected csv, tsv or jsonl", s)),
+        }
+    }
+}
+
+// 结果输出目标：每检测到一个存活代理调用一次 write，扫描结束时调用 finish
+pub trait OutputSink: Send {
+    fn write(&mut self, proxy: &ProxyData, meta: &CheckMeta) -> io::Result<()>;
+
+    fn finish(&mut self) -> io::Result<()>;
+}
+
+// 按配置创建所有输出目标，可同时启用多个
+pub fn build_sinks(config: &OutputConfig) -> io::Result<Vec<Box<dyn OutputSink>>> {
+    let mut sinks: Vec<Box<dyn OutputSink>> = Vec::new();
+
+    if let Some(path) = &config.csv {
+        sinks.push(Box::new(CsvSink::new(BufWriter::new(create_output_file(path)?), config.csv_header)?));
+        eprintln!("File {} has been cleared or created before scanning process started.", path);
+    }
+
+    if let Some(path) = &config.jsonl {
+        sinks.push(Box::new(JsonLinesSink::new(BufWriter::new(create_output_file(path)?))));
+        eprintln!("JSON Lines results will be written to {}", path);
+    }
+
+    if let Some(path) = &config.tsv {
+        sinks.push(Box::new(CsvSink::tsv(BufWriter::new(create_output_file(path)?), config.csv_header)?));
+        eprintln!("TSV results will be written to {}", path);
+    }
+
+    // Stdout is line-buffered already, so records show up as soon as they are written
+    match config.stdout {
+        Some(OutputFormat::Csv) => sinks.push(Box::new(CsvSink::new(io::stdout(), config.csv_header)?)),
+        Some(OutputFormat::Tsv) => sinks.push(Box::new(CsvSink::tsv(io::stdout(), config.csv_header)?)),
+        Some(OutputFormat::Jsonl) => sinks.push(Box::new(JsonLinesSink::new(io::stdout()))),
+        None => {}
+    }
+
+    Ok(sinks)
+}
+
+// Create output directory if it doesn't exist, then truncate the file
+fn create_output_file(path: &str) -> io::Result<File> {
+    if let Some(parent) = Path::new(path).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    File::create(path)
+}
+
+// RFC 4180：字段包含逗号、双引号或换行时用双引号包裹，内部双引号写两次
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+pub fn csv_line(fields: &[String]) -> String {
+    fields.iter().map(|f| csv_escape(f)).collect::<Vec<_>>().join(",")
+}
+
+// TSV 没有引号转义，字段中的制表符与换行替换为空格
+pub fn tsv_line(fields: &[String]) -> String {
+    fields.iter().map(|f| f.replace(['\t', '\r', '\n'], " ")).collect::<Vec<_>>().join("\t")
+}
+
+// CSV 与 TSV 共用，只有分隔与转义方式不同
+pub struct CsvSink<W: Write + Send> {
+    writer: W,
+    line: fn(&[String]) -> String,
+}
+
+impl<W: Write + Send> CsvSink<W> {
+    pub fn new(writer: W, header: bool) -> io::Result<Self> {
+        Self::with_format(writer, header, csv_line)
+    }
+
+    pub fn tsv(writer: W, header: bool) -> io::Result<Self> {
+        Self::with_format(writer, header, tsv_line)
+    }
+
+    fn with_format(mut writer: W, header: bool, line: fn(&[String]) -> String) -> io::Result<Self> {
+        if header {
+            let names: Vec<String> = CSV_HEADER.iter().map(|name| name.to_string()).collect();
+            writeln!(writer, "{}", line(&names))?;
+        }
+        Ok(CsvSink { writer, line })
+    }
+}
+
+impl<W: Write + Send> OutputSink for CsvSink<W> {
+    fn write(&mut self, proxy: &ProxyData, _meta: &CheckMeta) -> io::Result<()> {
+        writeln!(self.writer, "{}", (self.line)(&proxy.csv_fields()))
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+// JSON Lines：每行一个对象，包含 ProxyData 全部字段与检测元数据
+pub struct JsonLinesSink<W: Write + Send> {
+    writer: W,
+}
+
+impl<W: Write + Send> JsonLinesSink<W> {
+    pub fn new(writer: W) -> Self {
+        JsonLinesSink { writer }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonRecord<'a> {
+    #[serde(flatten)]
+    proxy: &'a ProxyData,
+    #[serde(flatten)]
+    meta: &'a CheckMeta,
+}
+
+impl<W: Write + Send> OutputSink for JsonLinesSink<W> {
+    fn write(&mut self, proxy: &ProxyData, meta: &CheckMeta) -> io::Result<()> {
+        serde_json::to_writer(&mut self.writer, &JsonRecord { proxy, meta })?;
+        self.writer.write_all(b"\n")
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+// 未通过检测的代理及原因，CSV 格式: ip,port,reason,detail
+pub struct RejectedWriter {
+    writer: BufWriter<File>,
+}
+
+impl RejectedWriter {
+    pub fn create(path: &str) -> io::Result<Self> {
+        let mut writer = BufWriter::new(create_output_file(path)?);
+        writeln!(writer, "ip,port,reason,detail")?;
+        eprintln!("Rejected proxies will be written to {}", path);
+        Ok(RejectedWriter { writer })
+    }
+
+    pub fn write(&mut self, ip: &str, port: Option<u16>, error: &CheckError) -> io::Result<()> {
+        let fields = [
+            ip.to_string(),
+            port.map(|p| p.to_string()).unwrap_or_default(),
+            error.kind().to_string(),
+            error.to_string(),
+        ];
+        writeln!(self.writer, "{}", csv_line(&fields))
+    }
+
+    pub fn finish(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proxy(org_name: &str) -> ProxyData {
+        ProxyData {
+            ip: "1.2.3.4".to_string(),
+            port: 443,
+            org_name: org_name.to_string(),
+            ..ProxyData::default()
+        }
+    }
+
+    fn meta() -> CheckMeta {
+        CheckMeta {
+            checked_at: chrono::Utc::now(),
+            resolver: String::new(),
+            resolvers_passed: 1,
+            resolvers_checked: 1,
+        }
+    }
+
+    #[test]
+    fn header_matches_csv_fields() {
+        assert_eq!(CSV_HEADER.len(), proxy("").csv_fields().len());
+    }
+
+    #[test]
+    fn csv_escape_quotes_only_when_needed() {
+        assert_eq!(csv_escape("Cloudflare"), "Cloudflare");
+        assert_eq!(csv_escape(""), "");
+        assert_eq!(csv_escape("Example, Inc."), "\"Example, Inc.\"");
+        assert_eq!(csv_escape("The \"Best\" ISP"), "\"The \"\"Best\"\" ISP\"");
+        assert_eq!(csv_escape("line\nbreak"), "\"line\nbreak\"");
+        assert_eq!(csv_escape("cr\rlf"), "\"cr\rlf\"");
+    }
+
+    #[test]
+    fn csv_line_keeps_org_name_in_one_column() {
+        let fields = proxy("Example, \"Inc.\"\nEU").csv_fields();
+        let line = csv_line(&fields);
+        assert!(line.starts_with("1.2.3.4,443,,,,,,\"Example, \"\"Inc.\"\"\nEU\","));
+        // 引号外的逗号数等于列数 - 1
+        let mut in_quotes = false;
+        let separators = line
+            .chars()
+            .filter(|&c| {
+                if c == '"' {
+                    in_quotes = !in_quotes;
+                }
+                c == ',' && !in_quotes
+            })
+            .count();
+        assert_eq!(separators, CSV_HEADER.len() - 1);
+    }
+
+    #[test]
+    fn tsv_line_replaces_separators() {
+        let fields = vec!["a\tb".to_string(), "c\r\nd".to_string(), "e, \"f\"".to_string()];
+        assert_eq!(tsv_line(&fields), "a b\tc  d\te, \"f\"");
+    }
+
+    #[test]
+    fn sinks_write_header_and_rows() {
+        let mut csv = CsvSink::new(Vec::new(), true).unwrap();
+        csv.write(&proxy("Example, Inc."), &meta()).unwrap();
+        let output = String::from_utf8(csv.writer).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], CSV_HEADER.join(","));
+        assert!(lines[1].contains("\"Example, Inc.\""));
+
+        let mut tsv = CsvSink::tsv(Vec::new(), false).unwrap();
+        tsv.write(&proxy("Example\tInc."), &meta()).unwrap();
+        let output = String::from_utf8(tsv.writer).unwrap();
+        assert_eq!(output.lines().count(), 1);
+        assert_eq!(output.strip_suffix('\n').unwrap().split('\t').count(), CSV_HEADER.len());
+    }
+}
@@ -0,0 +1,341 @@
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::sync::{Notify, OwnedSemaphorePermit, Semaphore};
+
+use crate::config::ScanConfig;
+
+// 全局连接速率限制（令牌桶）：每秒补充 rate 个令牌，最多积攒 1 秒的量
+// 令牌不足时先预订（余额可为负），再按欠额计算等待时间，先到先得
+pub struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    state: Mutex<Bucket>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    // rate <= 0 表示不限速
+    pub fn new(rate: f64) -> Option<Self> {
+        if rate <= 0.0 {
+            return None;
+        }
+        let burst = rate.max(1.0);
+        Some(RateLimiter {
+            rate,
+            burst,
+            state: Mutex::new(Bucket {
+                tokens: burst,
+                updated: Instant::now(),
+            }),
+        })
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    // 每建立一个连接前调用一次
+    pub async fn acquire(&self) {
+        let wait = {
+            let mut bucket = self.state.lock().unwrap();
+            let now = Instant::now();
+            let refill = now.duration_since(bucket.updated).as_secs_f64() * self.rate;
+            bucket.tokens = (bucket.tokens + refill).min(self.burst);
+            bucket.updated = now;
+            bucket.tokens -= 1.0;
+            if bucket.tokens >= 0.0 {
+                return;
+            }
+            Duration::from_secs_f64(-bucket.tokens / self.rate)
+        };
+        tokio::time::sleep(wait).await;
+    }
+}
+
+// 同一 IP（不同端口）与同一网段（IPv4 /24、IPv6 /48）同时进行的检测数上限，0 表示不限制
+// 名额不足时不等待，由调用方把地址放进 HostQueue，先检测其他主机
+pub struct HostLimiter {
+    per_ip: usize,
+    per_subnet: usize,
+    ips: SlotMap<IpAddr>,
+    subnets: SlotMap<IpAddr>,
+    // 每释放一个名额通知一次，用于重试 HostQueue 中暂缓的地址
+    released: Notify,
+}
+
+// 名额已满的是网段还是 IP
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Blocked {
+    Subnet,
+    Ip,
+}
+
+type SlotMap<K> = Mutex<HashMap<K, Arc<Semaphore>>>;
+
+// 持有期间占用该 IP 与网段各一个名额
+pub struct HostSlot<'a> {
+    limiter: &'a HostLimiter,
+    ip: Option<(IpAddr, OwnedSemaphorePermit)>,
+    subnet: Option<(IpAddr, OwnedSemaphorePermit)>,
+}
+
+impl HostLimiter {
+    pub fn new(config: &ScanConfig) -> Self {
+        HostLimiter {
+            per_ip: config.per_ip_concurrent,
+            per_subnet: config.per_subnet_concurrent,
+            ips: Mutex::new(HashMap::new()),
+            subnets: Mutex::new(HashMap::new()),
+            released: Notify::new(),
+        }
+    }
+
+    // 立即取得网段与 IP 名额，任一已满时返回哪一个满了，已取得的名额随之释放
+    // 这里的释放不发通知，否则调度方会因自己的失败尝试被反复唤醒
+    pub fn try_acquire(&self, ip: IpAddr) -> Result<HostSlot<'_>, Blocked> {
+        let subnet = match self.per_subnet {
+            0 => None,
+            limit => {
+                let permit = try_acquire_slot(&self.subnets, subnet_of(ip), limit).ok_or(Blocked::Subnet)?;
+                Some((subnet_of(ip), permit))
+            }
+        };
+        let ip = match self.per_ip {
+            0 => None,
+            limit => match try_acquire_slot(&self.ips, ip, limit) {
+                Some(permit) => Some((ip, permit)),
+                None => {
+                    if let Some((subnet, permit)) = subnet {
+                        release_slot(&self.subnets, subnet, permit);
+                    }
+                    return Err(Blocked::Ip);
+                }
+            },
+        };
+        Ok(HostSlot { limiter: self, ip, subnet })
+    }
+
+    // 等待任意名额被释放
+    pub async fn released(&self) {
+        self.released.notified().await;
+    }
+}
+
+fn try_acquire_slot<K: Eq + Hash + Copy>(slots: &SlotMap<K>, key: K, limit: usize) -> Option<OwnedSemaphorePermit> {
+    let mut slots = slots.lock().unwrap();
+    let semaphore = slots.entry(key).or_insert_with(|| Arc::new(Semaphore::new(limit)));
+    let permit = Arc::clone(semaphore).try_acquire_owned().ok();
+    // 新建的条目没有取到名额时不留在 map 中
+    if permit.is_none() && Arc::strong_count(semaphore) == 1 {
+        slots.remove(&key);
+    }
+    permit
+}
+
+// 没有任务持有或等待时删除条目，避免大列表扫描时 map 无限增长
+fn release_slot<K: Eq + Hash>(slots: &SlotMap<K>, key: K, permit: OwnedSemaphorePermit) {
+    let mut slots = slots.lock().unwrap();
+    drop(permit);
+    if slots.get(&key).is_some_and(|semaphore| Arc::strong_count(semaphore) == 1) {
+        slots.remove(&key);
+    }
+}
+
+impl Drop for HostSlot<'_> {
+    fn drop(&mut self) {
+        if let Some((ip, permit)) = self.ip.take() {
+            release_slot(&self.limiter.ips, ip, permit);
+        }
+        if let Some((subnet, permit)) = self.subnet.take() {
+            release_slot(&self.limiter.subnets, subnet, permit);
+        }
+        self.limiter.released.notify_one();
+    }
+}
+
+// 因名额已满而暂缓的条目，按网段分组保持输入顺序
+pub struct HostQueue<T> {
+    subnets: HashMap<IpAddr, VecDeque<(IpAddr, T)>>,
+    len: usize,
+}
+
+impl<T> HostQueue<T> {
+    pub fn new() -> Self {
+        HostQueue {
+            subnets: HashMap::new(),
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn push(&mut self, ip: IpAddr, item: T) {
+        self.subnets.entry(subnet_of(ip)).or_default().push_back((ip, item));
+        self.len += 1;
+    }
+
+    // 取出第一个已能取得名额的条目；网段已满时跳过整个网段，IP 已满时继续看同网段的下一个 IP
+    pub fn pop_ready<'a>(&mut self, hosts: &'a HostLimiter) -> Option<(T, HostSlot<'a>)> {
+        let mut found = None;
+        for (&subnet, queue) in self.subnets.iter_mut() {
+            for index in 0..queue.len() {
+                match hosts.try_acquire(queue[index].0) {
+                    Ok(slot) => {
+                        let (_, item) = queue.remove(index).expect("index is within the queue");
+                        found = Some((subnet, item, slot));
+                        break;
+                    }
+                    Err(Blocked::Subnet) => break,
+                    Err(Blocked::Ip) => {}
+                }
+            }
+            if found.is_some() {
+                break;
+            }
+        }
+
+        let (subnet, item, slot) = found?;
+        self.len -= 1;
+        if self.subnets.get(&subnet).is_some_and(VecDeque::is_empty) {
+            self.subnets.remove(&subnet);
+        }
+        Some((item, slot))
+    }
+}
+
+fn subnet_of(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, c, _] = v4.octets();
+            IpAddr::from([a, b, c, 0])
+        }
+        IpAddr::V6(v6) => {
+            let s = v6.segments();
+            IpAddr::from([s[0], s[1], s[2], 0, 0, 0, 0, 0])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(per_ip: usize, per_subnet: usize) -> HostLimiter {
+        HostLimiter::new(&ScanConfig {
+            per_ip_concurrent: per_ip,
+            per_subnet_concurrent: per_subnet,
+            ..ScanConfig::default()
+        })
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn try_acquire_reports_which_limit_is_full() {
+        let hosts = limiter(1, 2);
+        let first = hosts.try_acquire(ip("10.0.0.1")).unwrap();
+        assert_eq!(hosts.try_acquire(ip("10.0.0.1")).err(), Some(Blocked::Ip));
+        let second = hosts.try_acquire(ip("10.0.0.2")).unwrap();
+        assert_eq!(hosts.try_acquire(ip("10.0.0.3")).err(), Some(Blocked::Subnet));
+        // 其他网段不受影响
+        assert!(hosts.try_acquire(ip("10.0.1.1")).is_ok());
+        drop(first);
+        assert!(hosts.try_acquire(ip("10.0.0.3")).is_ok());
+        drop(second);
+    }
+
+    #[test]
+    fn failed_ip_acquire_returns_the_subnet_slot() {
+        let hosts = limiter(1, 2);
+        let _held = hosts.try_acquire(ip("10.0.0.1")).unwrap();
+        for _ in 0..5 {
+            assert_eq!(hosts.try_acquire(ip("10.0.0.1")).err(), Some(Blocked::Ip));
+        }
+        // 失败的尝试没有占住网段名额
+        assert!(hosts.try_acquire(ip("10.0.0.2")).is_ok());
+    }
+
+    #[test]
+    fn slots_are_removed_when_released() {
+        let hosts = limiter(1, 1);
+        let slot = hosts.try_acquire(ip("2001:db8::1")).unwrap();
+        assert!(hosts.try_acquire(ip("2001:db8::2")).is_err());
+        assert_eq!(hosts.ips.lock().unwrap().len(), 1);
+        assert_eq!(hosts.subnets.lock().unwrap().len(), 1);
+        drop(slot);
+        assert!(hosts.ips.lock().unwrap().is_empty());
+        assert!(hosts.subnets.lock().unwrap().is_empty());
+
+        // 上限为 0 时不记录任何条目
+        let zero = limiter(0, 0);
+        let slot = zero.try_acquire(ip("10.0.0.1")).unwrap();
+        assert!(zero.ips.lock().unwrap().is_empty() && zero.subnets.lock().unwrap().is_empty());
+        drop(slot);
+    }
+
+    #[test]
+    fn pop_ready_skips_blocked_ips_within_a_subnet() {
+        let hosts = limiter(1, 0);
+        let held = hosts.try_acquire(ip("10.0.0.1")).unwrap();
+        let mut queue = HostQueue::new();
+        queue.push(ip("10.0.0.1"), "a:80");
+        queue.push(ip("10.0.0.1"), "a:443");
+        queue.push(ip("10.0.0.2"), "b:80");
+        assert_eq!(queue.len(), 3);
+
+        let (item, slot) = queue.pop_ready(&hosts).unwrap();
+        assert_eq!(item, "b:80");
+        assert!(queue.pop_ready(&hosts).is_none());
+        drop(slot);
+        drop(held);
+
+        // 同一 IP 的条目按输入顺序取出
+        let (item, slot) = queue.pop_ready(&hosts).unwrap();
+        assert_eq!(item, "a:80");
+        assert!(queue.pop_ready(&hosts).is_none());
+        drop(slot);
+        assert_eq!(queue.pop_ready(&hosts).unwrap().0, "a:443");
+        assert!(queue.is_empty());
+        assert!(queue.subnets.is_empty());
+    }
+
+    #[test]
+    fn pop_ready_skips_full_subnets() {
+        let hosts = limiter(0, 1);
+        let held = hosts.try_acquire(ip("10.0.0.9")).unwrap();
+        let mut queue = HostQueue::new();
+        queue.push(ip("10.0.0.1"), 1);
+        queue.push(ip("10.0.0.2"), 2);
+        queue.push(ip("10.0.1.1"), 3);
+
+        let (item, slot) = queue.pop_ready(&hosts).unwrap();
+        assert_eq!(item, 3);
+        assert!(queue.pop_ready(&hosts).is_none());
+        assert_eq!(queue.len(), 2);
+        drop(slot);
+        drop(held);
+
+        let (item, slot) = queue.pop_ready(&hosts).unwrap();
+        assert_eq!(item, 1);
+        assert!(queue.pop_ready(&hosts).is_none());
+        drop(slot);
+        assert_eq!(queue.pop_ready(&hosts).unwrap().0, 2);
+        assert!(queue.subnets.is_empty());
+    }
+}
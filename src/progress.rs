@@ -0,0 +1,151 @@
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::task::JoinHandle;
+
+use crate::concurrency::ConcurrencyLimiter;
+
+// 终端上原地刷新的间隔
+const TTY_INTERVAL: Duration = Duration::from_millis(500);
+// CI 日志等非终端环境下打印普通行的间隔，避免刷屏
+const PLAIN_INTERVAL: Duration = Duration::from_secs(15);
+
+// 扫描进度，写到 stderr，不干扰 stdout 上的结果流
+pub struct Progress {
+    // 已读入的条目数；输入是流式读取的，input_done 之前总数还会增长
+    total: AtomicUsize,
+    input_done: AtomicBool,
+    checked: AtomicUsize,
+    live: AtomicUsize,
+    started: Instant,
+    tty: bool,
+    // 自适应模式下在进度行中显示当前并发上限
+    limiter: Arc<ConcurrencyLimiter>,
+}
+
+impl Progress {
+    pub fn new(limiter: Arc<ConcurrencyLimiter>) -> Arc<Self> {
+        Arc::new(Progress {
+            total: AtomicUsize::new(0),
+            input_done: AtomicBool::new(false),
+            checked: AtomicUsize::new(0),
+            live: AtomicUsize::new(0),
+            started: Instant::now(),
+            tty: io::stderr().is_terminal(),
+            limiter,
+        })
+    }
+
+    pub fn add_total(&self, count: usize) {
+        self.total.fetch_add(count, Ordering::Relaxed);
+    }
+
+    // 文件输入在扫描前已完整读过一遍，总数从一开始就确定
+    pub fn set_total(&self, total: usize) {
+        self.total.store(total, Ordering::Relaxed);
+        self.input_done.store(true, Ordering::Relaxed);
+    }
+
+    // 输入全部读完，此后才能计算百分比与 ETA
+    pub fn finish_input(&self) {
+        self.input_done.store(true, Ordering::Relaxed);
+    }
+
+    // 每处理完一行输入调用一次（无论结果如何）
+    pub fn inc(&self) {
+        self.checked.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_live(&self) {
+        self.live.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // 在终端上打印其他日志前先清掉进度行，避免两者混在同一行
+    pub fn clear(&self) {
+        if self.tty {
+            eprint!("\r\x1b[2K");
+        }
+    }
+
+    // 启动后台刷新任务，扫描结束后调用 finish 停止并打印最终进度
+    pub fn spawn(self: &Arc<Self>) -> JoinHandle<()> {
+        let progress = Arc::clone(self);
+        let period = if progress.tty { TTY_INTERVAL } else { PLAIN_INTERVAL };
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            // 第一次 tick 立即返回，跳过以免在开始时打印 0 进度
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                progress.print();
+            }
+        })
+    }
+
+    pub fn finish(&self, handle: JoinHandle<()>) {
+        handle.abort();
+        self.print();
+        if self.tty {
+            eprintln!();
+        }
+    }
+
+    fn print(&self) {
+        let line = self.line();
+        let mut stderr = io::stderr().lock();
+        let _ = if self.tty {
+            write!(stderr, "\r\x1b[2K{}", line)
+        } else {
+            writeln!(stderr, "{}", line)
+        };
+        let _ = stderr.flush();
+    }
+
+    fn line(&self) -> String {
+        let checked = self.checked.load(Ordering::Relaxed);
+        let live = self.live.load(Ordering::Relaxed);
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let rate = if elapsed > 0.0 { checked as f64 / elapsed } else { 0.0 };
+
+        let total = self.total.load(Ordering::Relaxed);
+        // 输入尚未读完时总数未知，只显示已读入的数量
+        let (total, percent, eta) = if !self.input_done.load(Ordering::Relaxed) {
+            (format!("{}+", total), String::new(), "--:--:--".to_string())
+        } else {
+            let percent = if total > 0 { checked as f64 * 100.0 / total as f64 } else { 100.0 };
+            let eta = if rate > 0.0 {
+                format_duration(total.saturating_sub(checked) as f64 / rate)
+            } else {
+                "--:--:--".to_string()
+            };
+            (total.to_string(), format!(" ({:.1}%)", percent), eta)
+        };
+
+        let concurrency = if self.limiter.is_adaptive() {
+            format!(" | concurrency {}", self.limiter.limit())
+        } else {
+            String::new()
+        };
+
+        format!(
+            "⏳ {}/{}{} | live {} | failed {} | {:.1} checks/s | elapsed {} | ETA {}{}",
+            checked,
+            total,
+            percent,
+            live,
+            checked.saturating_sub(live),
+            rate,
+            format_duration(elapsed),
+            eta,
+            concurrency
+        )
+    }
+}
+
+fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    format!("{:02}:{:02}:{:02}", seconds / 3600, (seconds % 3600) / 60, seconds % 60)
+}
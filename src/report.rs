@@ -0,0 +1,235 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::error::{CheckError, FilterKind};
+use crate::input::InputSummary;
+use crate::ProxyData;
+
+// 表格中每个分布只显示前 N 项，JSON 报告中保留全部
+const TABLE_TOP_N: usize = 10;
+
+// 扫描过程中累计的统计数据
+#[derive(Default)]
+pub struct ScanStats {
+    parse_errors: usize,
+    same_as_origin: usize,
+    live: usize,
+    live_after_retry: usize,
+    dead: BTreeMap<&'static str, usize>,
+    filtered: BTreeMap<&'static str, usize>,
+    by_country: BTreeMap<String, usize>,
+    by_asn: BTreeMap<String, usize>,
+    by_port: BTreeMap<u16, usize>,
+    by_source: BTreeMap<String, usize>,
+    connect_ms: Vec<u32>,
+    tls_handshake_ms: Vec<u32>,
+    ttfb_ms: Vec<u32>,
+    total_ms: Vec<u32>,
+}
+
+// scan_report.json 的内容
+#[derive(Serialize)]
+pub struct ScanReport {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub duration_seconds: f64,
+    pub total_inputs: usize,
+    pub parse_errors: usize,
+    // 读取时按保留网段、FireHOL 或排除列表丢弃的地址，不计入 total_inputs
+    pub excluded_total: usize,
+    pub excluded: BTreeMap<&'static str, usize>,
+    pub live: usize,
+    // 重试后才成功的存活代理
+    pub live_after_retry: usize,
+    pub same_as_origin: usize,
+    pub dead_total: usize,
+    pub dead: BTreeMap<&'static str, usize>,
+    pub filtered_total: usize,
+    pub filtered: BTreeMap<&'static str, usize>,
+    pub by_country: BTreeMap<String, usize>,
+    pub by_asn: BTreeMap<String, usize>,
+    pub by_port: BTreeMap<u16, usize>,
+    // 各输入文件贡献的存活代理数
+    pub by_source: BTreeMap<String, usize>,
+    pub latency_ms: BTreeMap<&'static str, Percentiles>,
+}
+
+#[derive(Serialize)]
+pub struct Percentiles {
+    pub min: u32,
+    pub p50: u32,
+    pub p90: u32,
+    pub p99: u32,
+    pub max: u32,
+}
+
+impl ScanStats {
+    pub fn record_live(&mut self, proxy: &ProxyData) {
+        self.live += 1;
+        if proxy.attempts > 1 {
+            self.live_after_retry += 1;
+        }
+
+        let country = if proxy.country_code.is_empty() { "unknown" } else { &proxy.country_code };
+        *self.by_country.entry(country.to_string()).or_insert(0) += 1;
+
+        let asn = if proxy.asn_number.is_empty() {
+            "unknown".to_string()
+        } else {
+            format!("AS{} {}", proxy.asn_number, proxy.org_name)
+        };
+        *self.by_asn.entry(asn).or_insert(0) += 1;
+
+        *self.by_port.entry(proxy.port).or_insert(0) += 1;
+        *self.by_source.entry(proxy.source.clone()).or_insert(0) += 1;
+
+        self.connect_ms.push(proxy.timings.connect_ms);
+        self.tls_handshake_ms.push(proxy.timings.tls_handshake_ms);
+        self.ttfb_ms.push(proxy.timings.ttfb_ms);
+        self.total_ms.push(proxy.timings.total_ms);
+    }
+
+    pub fn record_failure(&mut self, error: &CheckError) {
+        match error {
+            CheckError::InvalidInput(_) => self.parse_errors += 1,
+            CheckError::SameAsOrigin => self.same_as_origin += 1,
+            CheckError::Filtered { kind, .. } => {
+                let filter = match kind {
+                    FilterKind::InvalidIp => "invalid_ip",
+                    FilterKind::Anonymous(_) => "anonymous_db",
+                    FilterKind::AbuseIpDb => "abuseipdb",
+                    FilterKind::FireHol => "firehol",
+                };
+                *self.filtered.entry(filter).or_insert(0) += 1;
+            }
+            _ => *self.dead.entry(error.kind()).or_insert(0) += 1,
+        }
+    }
+
+    pub fn into_report(self, input: &InputSummary, started_at: DateTime<Utc>) -> ScanReport {
+        let finished_at = Utc::now();
+
+        let mut latency_ms = BTreeMap::new();
+        for (name, mut values) in [
+            ("connect", self.connect_ms),
+            ("tls_handshake", self.tls_handshake_ms),
+            ("ttfb", self.ttfb_ms),
+            ("total", self.total_ms),
+        ] {
+            if let Some(percentiles) = Percentiles::from_values(&mut values) {
+                latency_ms.insert(name, percentiles);
+            }
+        }
+
+        ScanReport {
+            started_at,
+            finished_at,
+            duration_seconds: (finished_at - started_at).num_milliseconds() as f64 / 1000.0,
+            total_inputs: input.total_inputs(),
+            parse_errors: self.parse_errors,
+            excluded_total: input.excluded.values().sum(),
+            excluded: input.excluded.clone(),
+            live: self.live,
+            live_after_retry: self.live_after_retry,
+            same_as_origin: self.same_as_origin,
+            dead_total: self.dead.values().sum(),
+            dead: self.dead,
+            filtered_total: self.filtered.values().sum(),
+            filtered: self.filtered,
+            by_country: self.by_country,
+            by_asn: self.by_asn,
+            by_port: self.by_port,
+            by_source: self.by_source,
+            latency_ms,
+        }
+    }
+}
+
+impl Percentiles {
+    // 最近秩法（nearest-rank）计算百分位
+    fn from_values(values: &mut [u32]) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        values.sort_unstable();
+
+        let rank = |p: f64| {
+            let index = ((p / 100.0) * values.len() as f64).ceil() as usize;
+            values[index.clamp(1, values.len()) - 1]
+        };
+
+        Some(Percentiles {
+            min: values[0],
+            p50: rank(50.0),
+            p90: rank(90.0),
+            p99: rank(99.0),
+            max: values[values.len() - 1],
+        })
+    }
+}
+
+impl ScanReport {
+    pub fn write_json(&self, path: &str) -> io::Result<()> {
+        if let Some(parent) = Path::new(path).parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+
+    // 人类可读的汇总表格
+    pub fn print_table(&self) {
+        eprintln!("═══════════════════════════════════════");
+        eprintln!("📊 Scan summary ({:.1}s)", self.duration_seconds);
+        eprintln!("═══════════════════════════════════════");
+        eprintln!("  {:<28} {:>8}", "Total inputs", self.total_inputs);
+        eprintln!("  {:<28} {:>8}", "Parse errors", self.parse_errors);
+        eprintln!("  {:<28} {:>8}", "Excluded (not contacted)", self.excluded_total);
+        for (reason, count) in &self.excluded {
+            eprintln!("    {:<26} {:>8}", reason, count);
+        }
+        eprintln!("  {:<28} {:>8}", "Live ✅", self.live);
+        eprintln!("    {:<26} {:>8}", "after retry", self.live_after_retry);
+        eprintln!("  {:<28} {:>8}", "Same IP as original", self.same_as_origin);
+        eprintln!("  {:<28} {:>8}", "Dead ❌", self.dead_total);
+        for (kind, count) in &self.dead {
+            eprintln!("    {:<26} {:>8}", kind, count);
+        }
+        eprintln!("  {:<28} {:>8}", "Filtered 🚫", self.filtered_total);
+        for (filter, count) in &self.filtered {
+            eprintln!("    {:<26} {:>8}", filter, count);
+        }
+
+        print_top("Top countries", self.by_country.iter().map(|(k, v)| (k.clone(), *v)));
+        print_top("Top ASNs", self.by_asn.iter().map(|(k, v)| (k.clone(), *v)));
+        print_top("Top ports", self.by_port.iter().map(|(k, v)| (k.to_string(), *v)));
+        print_top("Top sources", self.by_source.iter().map(|(k, v)| (k.clone(), *v)));
+
+        if !self.latency_ms.is_empty() {
+            eprintln!("  Latency (ms)                  min    p50    p90    p99    max");
+            for (name, p) in &self.latency_ms {
+                eprintln!("    {:<24} {:>6} {:>6} {:>6} {:>6} {:>6}", name, p.min, p.p50, p.p90, p.p99, p.max);
+            }
+        }
+        eprintln!("═══════════════════════════════════════");
+    }
+}
+
+fn print_top(title: &str, entries: impl Iterator<Item = (String, usize)>) {
+    let mut entries: Vec<(String, usize)> = entries.collect();
+    if entries.is_empty() {
+        return;
+    }
+    entries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    eprintln!("  {}", title);
+    for (key, count) in entries.iter().take(TABLE_TOP_N) {
+        eprintln!("    {:<26} {:>8}", key, count);
+    }
+}
@@ -0,0 +1,137 @@
+use std::net::IpAddr;
+
+use serde_json::Value;
+
+use crate::checker::CfMeta;
+use crate::config::{ResolverConfig, ResponseFormat};
+use crate::error::CheckError;
+
+// 按 resolver 的格式解析响应体，得到 Cloudflare 看到的出口信息
+// json/plain 只能得到出口 IP，其余字段留空
+pub fn parse_echo(resolver: &ResolverConfig, body: &[u8]) -> Result<CfMeta, CheckError> {
+    let mut meta = match resolver.format {
+        ResponseFormat::Meta => serde_json::from_slice::<CfMeta>(body).map_err(invalid_json)?,
+        ResponseFormat::Trace => parse_trace(body),
+        ResponseFormat::Json => {
+            let value: Value = serde_json::from_slice(body).map_err(invalid_json)?;
+            let field = resolver.ip_field.as_deref().unwrap_or("ip");
+            CfMeta {
+                client_ip: value.get(field).and_then(Value::as_str).unwrap_or_default().to_string(),
+                ..CfMeta::default()
+            }
+        }
+        ResponseFormat::Plain => CfMeta {
+            client_ip: String::from_utf8_lossy(body).trim().to_string(),
+            ..CfMeta::default()
+        },
+    };
+
+    // 统一 IP 写法，便于不同 resolver 之间比较
+    meta.client_ip = meta
+        .client_ip
+        .trim()
+        .parse::<IpAddr>()
+        .map_err(|_| CheckError::MissingClientIp)?
+        .to_string();
+
+    Ok(meta)
+}
+
+// /cdn-cgi/trace 响应，每行一个 key=value:
+// fl=... h=example.com ip=1.2.3.4 ts=... visit_scheme=https uag=... colo=SIN http=http/1.1 loc=SG tls=TLSv1.3 ...
+fn parse_trace(body: &[u8]) -> CfMeta {
+    let mut meta = CfMeta::default();
+    for line in String::from_utf8_lossy(body).lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim().to_string();
+        match key.trim() {
+            "ip" => meta.client_ip = value,
+            "colo" => meta.colo = value,
+            "loc" => meta.country = value,
+            "http" => meta.http_protocol = value,
+            "tls" => meta.tls_version = value,
+            _ => {}
+        }
+    }
+    meta
+}
+
+fn invalid_json(e: serde_json::Error) -> CheckError {
+    CheckError::InvalidJson(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolver(spec: &str) -> ResolverConfig {
+        spec.parse().unwrap()
+    }
+
+    #[test]
+    fn meta_tolerates_null_and_mistyped_fields() {
+        let body = br#"{"clientIp": "1.2.3.4", "colo": null, "asn": "13335", "asOrganization": 42, "city": null, "country": ["SG"], "httpProtocol": "HTTP/1.1"}"#;
+        let meta = parse_echo(&resolver("speed.cloudflare.com"), body).unwrap();
+        assert_eq!(meta.client_ip, "1.2.3.4");
+        assert_eq!(meta.colo, "");
+        assert_eq!(meta.asn, 13335);
+        assert_eq!(meta.as_organization, "42");
+        assert_eq!(meta.city, "");
+        assert_eq!(meta.country, "");
+        assert_eq!(meta.http_protocol, "HTTP/1.1");
+    }
+
+    #[test]
+    fn meta_requires_a_valid_client_ip() {
+        for body in [&br#"{"colo": "SIN"}"#[..], br#"{"clientIp": null}"#, br#"{"clientIp": 5}"#, br#"{"clientIp": "nope"}"#] {
+            let result = parse_echo(&resolver("speed.cloudflare.com"), body);
+            assert!(matches!(result, Err(CheckError::MissingClientIp)), "{}", String::from_utf8_lossy(body));
+        }
+        assert!(matches!(parse_echo(&resolver("speed.cloudflare.com"), b"<html>"), Err(CheckError::InvalidJson(_))));
+    }
+
+    #[test]
+    fn trace_maps_known_keys() {
+        let body = b"fl=466f71\nh=speed.cloudflare.com\nip=2001:DB8:0:0::1 \nts=1700000000.123\nvisit_scheme=https\n\
+colo=SIN\nhttp=http/2\nloc=SG\ntls=TLSv1.3\nsni=plaintext\nwarp=off\n\ngarbage line\n";
+        let meta = parse_echo(&resolver("trace:speed.cloudflare.com"), body).unwrap();
+        assert_eq!(meta.client_ip, "2001:db8::1");
+        assert_eq!(meta.colo, "SIN");
+        assert_eq!(meta.country, "SG");
+        assert_eq!(meta.http_protocol, "http/2");
+        assert_eq!(meta.tls_version, "TLSv1.3");
+        assert_eq!(meta.asn, 0);
+        assert_eq!(meta.city, "");
+    }
+
+    #[test]
+    fn trace_requires_an_ip_line() {
+        let result = parse_echo(&resolver("trace:speed.cloudflare.com"), b"colo=SIN\nloc=SG\n");
+        assert!(matches!(result, Err(CheckError::MissingClientIp)));
+    }
+
+    #[test]
+    fn resolver_spec_defaults_path_by_format() {
+        let trace = resolver("trace:one.one.one.one");
+        assert_eq!((trace.host.as_str(), trace.path.as_str(), trace.format), ("one.one.one.one", "/cdn-cgi/trace", ResponseFormat::Trace));
+
+        let meta = resolver("speed.cloudflare.com");
+        assert_eq!((meta.host.as_str(), meta.path.as_str(), meta.format), ("speed.cloudflare.com", "/meta", ResponseFormat::Meta));
+
+        let json = resolver("JSON:echo.example.com/v1/ip");
+        assert_eq!((json.host.as_str(), json.path.as_str(), json.format), ("echo.example.com", "/v1/ip", ResponseFormat::Json));
+        assert_eq!(json.ip_field, None);
+
+        let plain = resolver("plain:ip.example.com/");
+        assert_eq!((plain.path.as_str(), plain.format), ("/", ResponseFormat::Plain));
+    }
+
+    #[test]
+    fn resolver_spec_rejects_bad_input() {
+        for spec in ["", "trace:", "meta:/meta", "xml:example.com", "example.com:443"] {
+            assert!(spec.parse::<ResolverConfig>().is_err(), "{}", spec);
+        }
+    }
+}
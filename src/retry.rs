@@ -0,0 +1,109 @@
+use std::time::Duration;
+
+use crate::config::RetryConfig;
+use crate::error::CheckError;
+use crate::Result;
+
+// 单个 resolver 检测失败后的重试策略：指数退避 + 随机抖动，只对指定的错误类别重试
+pub struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    retry_on: Vec<String>,
+}
+
+impl RetryPolicy {
+    pub fn new(config: &RetryConfig) -> Result<Self> {
+        if config.max_attempts == 0 {
+            return Err("retry.max_attempts must be at least 1".into());
+        }
+        for kind in &config.retry_on {
+            if !CheckError::KINDS.contains(&kind.as_str()) {
+                return Err(format!(
+                    "Unknown error class '{}' in retry.retry_on, expected one of: {}",
+                    kind,
+                    CheckError::KINDS.join(", ")
+                ).into());
+            }
+        }
+
+        Ok(RetryPolicy {
+            max_attempts: config.max_attempts,
+            base_delay: Duration::from_millis(config.base_delay_ms),
+            max_delay: Duration::from_millis(config.max_delay_ms.max(config.base_delay_ms)),
+            retry_on: config.retry_on.clone(),
+        })
+    }
+
+    // attempt 从 1 开始，表示刚失败的是第几次尝试
+    pub fn should_retry(&self, error: &CheckError, attempt: u32) -> bool {
+        attempt < self.max_attempts && self.retry_on.iter().any(|kind| kind == error.kind())
+    }
+
+    // 第 attempt 次失败后的等待时间：base * 2^(attempt-1)，不超过 max，
+    // 再在 [delay/2, delay] 内随机取值，避免大量代理同时重试
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(16);
+        let delay = self.base_delay.saturating_mul(1 << exponent).min(self.max_delay);
+        let half = delay.as_millis() as u64 / 2;
+        Duration::from_millis(half + fastrand::u64(0..=half))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(max_attempts: u32, base_delay_ms: u64, max_delay_ms: u64) -> RetryPolicy {
+        RetryPolicy::new(&RetryConfig { max_attempts, base_delay_ms, max_delay_ms, ..RetryConfig::default() }).unwrap()
+    }
+
+    #[test]
+    fn retries_listed_kinds_until_max_attempts() {
+        let policy = policy(3, 100, 1000);
+        assert!(policy.should_retry(&CheckError::ReadTimeout, 1));
+        assert!(policy.should_retry(&CheckError::ConnectionReset, 2));
+        assert!(!policy.should_retry(&CheckError::ReadTimeout, 3));
+        assert!(!policy.should_retry(&CheckError::InvalidJson(String::new()), 1));
+    }
+
+    #[test]
+    fn default_never_retries_connect_errors() {
+        let policy = RetryPolicy::new(&RetryConfig { max_attempts: 5, ..RetryConfig::default() }).unwrap();
+        for attempt in 1..5 {
+            assert!(!policy.should_retry(&CheckError::ConnectRefused, attempt));
+            assert!(!policy.should_retry(&CheckError::ConnectTimeout, attempt));
+        }
+    }
+
+    #[test]
+    fn backoff_grows_exponentially_with_jitter() {
+        let policy = policy(10, 100, 60_000);
+        for (attempt, delay) in [(1, 100), (2, 200), (3, 400), (4, 800)] {
+            for _ in 0..50 {
+                let wait = policy.backoff(attempt).as_millis() as u64;
+                assert!((delay / 2..=delay).contains(&wait), "attempt {} waited {}ms", attempt, wait);
+            }
+        }
+    }
+
+    #[test]
+    fn backoff_is_capped_by_max_delay() {
+        let capped = policy(10, 500, 2000);
+        for attempt in [3, 4, 10, 1000] {
+            for _ in 0..50 {
+                let wait = capped.backoff(attempt).as_millis() as u64;
+                assert!((1000..=2000).contains(&wait), "attempt {} waited {}ms", attempt, wait);
+            }
+        }
+        // max 小于 base 时按 base 计
+        let wait = policy(3, 500, 100).backoff(2).as_millis() as u64;
+        assert!((250..=500).contains(&wait));
+    }
+
+    #[test]
+    fn rejects_invalid_config() {
+        assert!(RetryPolicy::new(&RetryConfig { max_attempts: 0, ..RetryConfig::default() }).is_err());
+        assert!(RetryPolicy::new(&RetryConfig { retry_on: vec!["timeout".into()], ..RetryConfig::default() }).is_err());
+    }
+}
@@ -0,0 +1,219 @@
+use std::fs;
+
+use tokio::net::TcpStream;
+
+use crate::config::TlsConfig;
+use crate::error::CheckError;
+use crate::Result;
+
+#[cfg(not(any(feature = "native-tls", feature = "rustls")))]
+compile_error!("cekproxy needs a TLS backend: enable the `native-tls` (default) or `rustls` feature");
+
+// 所有检测共用的 TLS 客户端，启动时构建一次
+// SNI 默认为目标主机名，配置 sni 后统一使用该值，可与 HTTP Host 头不同
+pub struct TlsClient {
+    connector: backend::Connector,
+    sni: Option<String>,
+}
+
+impl TlsClient {
+    pub fn new(config: &TlsConfig) -> Result<Self> {
+        if !config.builtin_roots && config.ca_bundle.is_none() {
+            return Err("tls.builtin_roots = false requires tls.ca_bundle".into());
+        }
+
+        let ca_bundle = match &config.ca_bundle {
+            Some(path) => {
+                let pem = fs::read(path)
+                    .map_err(|e| format!("Could not read CA bundle {}: {}", path, e))?;
+                Some((path.as_str(), pem))
+            }
+            None => None,
+        };
+
+        if config.insecure {
+            eprintln!("⚠️ TLS certificate verification is disabled (insecure mode), use for diagnostics only");
+        }
+
+        let connector = backend::Connector::new(
+            ca_bundle.as_ref().map(|(path, pem)| (*path, pem.as_slice())),
+            config.builtin_roots,
+            config.insecure,
+        )?;
+
+        Ok(TlsClient {
+            connector,
+            sni: config.sni.clone(),
+        })
+    }
+
+    // 连接 host 时实际发送的 SNI
+    pub fn sni<'a>(&'a self, host: &'a str) -> &'a str {
+        self.sni.as_deref().unwrap_or(host)
+    }
+
+    pub async fn connect(&self, host: &str, stream: TcpStream) -> std::result::Result<backend::TlsStream, CheckError> {
+        self.connector
+            .connect(self.sni(host), stream)
+            .await
+            .map_err(CheckError::from_tls_error)
+    }
+}
+
+// native-tls：Linux 上使用 OpenSSL，builtin roots 为系统证书库
+#[cfg(all(feature = "native-tls", not(feature = "rustls")))]
+mod backend {
+    use native_tls::{Certificate, TlsConnector as NativeTlsConnector};
+    use tokio::net::TcpStream;
+    use tokio_native_tls::TlsConnector as TokioTlsConnector;
+
+    use crate::Result;
+
+    pub type TlsStream = tokio_native_tls::TlsStream<TcpStream>;
+
+    pub struct Connector {
+        connector: TokioTlsConnector,
+    }
+
+    impl Connector {
+        pub fn new(ca_bundle: Option<(&str, &[u8])>, builtin_roots: bool, insecure: bool) -> Result<Self> {
+            let mut builder = NativeTlsConnector::builder();
+
+            if let Some((path, pem)) = ca_bundle {
+                let certs = Certificate::stack_from_pem(pem)
+                    .map_err(|e| format!("Invalid CA bundle {}: {}", path, e))?;
+                if certs.is_empty() {
+                    return Err(format!("CA bundle {} contains no certificates", path).into());
+                }
+                eprintln!("Loaded {} CA certificates from {}", certs.len(), path);
+                for cert in certs {
+                    builder.add_root_certificate(cert);
+                }
+            }
+
+            builder.disable_built_in_roots(!builtin_roots);
+
+            if insecure {
+                builder.danger_accept_invalid_certs(true);
+                builder.danger_accept_invalid_hostnames(true);
+            }
+
+            let connector = builder.build().map_err(|e| format!("Failed to build TLS connector: {}", e))?;
+
+            Ok(Connector {
+                connector: TokioTlsConnector::from(connector),
+            })
+        }
+
+        pub async fn connect(&self, sni: &str, stream: TcpStream) -> std::result::Result<TlsStream, String> {
+            self.connector.connect(sni, stream).await.map_err(|e| e.to_string())
+        }
+    }
+}
+
+// rustls：纯 Rust 实现，不依赖 OpenSSL，builtin roots 为 webpki-roots 内置的 Mozilla 证书列表
+#[cfg(feature = "rustls")]
+mod backend {
+    use std::sync::Arc;
+
+    use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+    use rustls::crypto::{self, CryptoProvider};
+    use rustls::pki_types::pem::PemObject;
+    use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+    use rustls::{ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme};
+    use tokio::net::TcpStream;
+    use tokio_rustls::TlsConnector;
+
+    use crate::Result;
+
+    pub type TlsStream = tokio_rustls::client::TlsStream<TcpStream>;
+
+    pub struct Connector {
+        connector: TlsConnector,
+    }
+
+    impl Connector {
+        pub fn new(ca_bundle: Option<(&str, &[u8])>, builtin_roots: bool, insecure: bool) -> Result<Self> {
+            let mut roots = RootCertStore::empty();
+            if builtin_roots {
+                roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+            }
+
+            if let Some((path, pem)) = ca_bundle {
+                let certs = CertificateDer::pem_slice_iter(pem)
+                    .collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(|e| format!("Invalid CA bundle {}: {}", path, e))?;
+                if certs.is_empty() {
+                    return Err(format!("CA bundle {} contains no certificates", path).into());
+                }
+                eprintln!("Loaded {} CA certificates from {}", certs.len(), path);
+                for cert in certs {
+                    roots.add(cert).map_err(|e| format!("Invalid CA certificate in {}: {}", path, e))?;
+                }
+            }
+
+            let provider = Arc::new(crypto::ring::default_provider());
+            let builder = ClientConfig::builder_with_provider(Arc::clone(&provider))
+                .with_safe_default_protocol_versions()
+                .map_err(|e| format!("Failed to build TLS config: {}", e))?;
+
+            let config = if insecure {
+                builder
+                    .dangerous()
+                    .with_custom_certificate_verifier(Arc::new(NoVerification(provider)))
+                    .with_no_client_auth()
+            } else {
+                builder.with_root_certificates(roots).with_no_client_auth()
+            };
+
+            Ok(Connector {
+                connector: TlsConnector::from(Arc::new(config)),
+            })
+        }
+
+        pub async fn connect(&self, sni: &str, stream: TcpStream) -> std::result::Result<TlsStream, String> {
+            let server_name = ServerName::try_from(sni.to_string())
+                .map_err(|e| format!("Invalid TLS server name '{}': {}", sni, e))?;
+            self.connector.connect(server_name, stream).await.map_err(|e| e.to_string())
+        }
+    }
+
+    // insecure 模式：接受任何证书与主机名，但仍校验握手签名
+    #[derive(Debug)]
+    struct NoVerification(Arc<CryptoProvider>);
+
+    impl ServerCertVerifier for NoVerification {
+        fn verify_server_cert(
+            &self,
+            _end_entity: &CertificateDer<'_>,
+            _intermediates: &[CertificateDer<'_>],
+            _server_name: &ServerName<'_>,
+            _ocsp_response: &[u8],
+            _now: UnixTime,
+        ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+            Ok(ServerCertVerified::assertion())
+        }
+
+        fn verify_tls12_signature(
+            &self,
+            message: &[u8],
+            cert: &CertificateDer<'_>,
+            dss: &DigitallySignedStruct,
+        ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+            crypto::verify_tls12_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+        }
+
+        fn verify_tls13_signature(
+            &self,
+            message: &[u8],
+            cert: &CertificateDer<'_>,
+            dss: &DigitallySignedStruct,
+        ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+            crypto::verify_tls13_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+        }
+
+        fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+            self.0.signature_verification_algorithms.supported_schemes()
+        }
+    }
+}